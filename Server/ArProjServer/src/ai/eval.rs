@@ -0,0 +1,129 @@
+use game::bitboard::EMPTY;
+use game::gamestate::GameState;
+use game::piece::Color;
+use game::piece::PieceType;
+
+// piece-square bonuses in centipawns, laid out like the grid from White's side: row 0 is
+// rank 8 and col 0 the a-file. Black reads them upside down.
+const PAWN_SQUARES: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [ 50,  50,  50,  50,  50,  50,  50,  50],
+    [ 10,  10,  20,  30,  30,  20,  10,  10],
+    [  5,   5,  10,  25,  25,  10,   5,   5],
+    [  0,   0,   0,  20,  20,   0,   0,   0],
+    [  5,  -5, -10,   0,   0, -10,  -5,   5],
+    [  5,  10,  10, -20, -20,  10,  10,   5],
+    [  0,   0,   0,   0,   0,   0,   0,   0]
+];
+
+const KNIGHT_SQUARES: [[i32; 8]; 8] = [
+    [-50, -40, -30, -30, -30, -30, -40, -50],
+    [-40, -20,   0,   0,   0,   0, -20, -40],
+    [-30,   0,  10,  15,  15,  10,   0, -30],
+    [-30,   5,  15,  20,  20,  15,   5, -30],
+    [-30,   0,  15,  20,  20,  15,   0, -30],
+    [-30,   5,  10,  15,  15,  10,   5, -30],
+    [-40, -20,   0,   5,   5,   0, -20, -40],
+    [-50, -40, -30, -30, -30, -30, -40, -50]
+];
+
+const BISHOP_SQUARES: [[i32; 8]; 8] = [
+    [-20, -10, -10, -10, -10, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,  10,  10,   5,   0, -10],
+    [-10,   5,   5,  10,  10,   5,   5, -10],
+    [-10,   0,  10,  10,  10,  10,   0, -10],
+    [-10,  10,  10,  10,  10,  10,  10, -10],
+    [-10,   5,   0,   0,   0,   0,   5, -10],
+    [-20, -10, -10, -10, -10, -10, -10, -20]
+];
+
+const ROOK_SQUARES: [[i32; 8]; 8] = [
+    [  0,   0,   0,   0,   0,   0,   0,   0],
+    [  5,  10,  10,  10,  10,  10,  10,   5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [ -5,   0,   0,   0,   0,   0,   0,  -5],
+    [  0,   0,   0,   5,   5,   0,   0,   0]
+];
+
+const QUEEN_SQUARES: [[i32; 8]; 8] = [
+    [-20, -10, -10,  -5,  -5, -10, -10, -20],
+    [-10,   0,   0,   0,   0,   0,   0, -10],
+    [-10,   0,   5,   5,   5,   5,   0, -10],
+    [ -5,   0,   5,   5,   5,   5,   0,  -5],
+    [  0,   0,   5,   5,   5,   5,   0,  -5],
+    [-10,   5,   5,   5,   5,   5,   0, -10],
+    [-10,   0,   5,   0,   0,   0,   0, -10],
+    [-20, -10, -10,  -5,  -5, -10, -10, -20]
+];
+
+// the middlegame king hides behind its pawns; without queens it walks to the centre instead
+const KING_SQUARES: [[i32; 8]; 8] = [
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-30, -40, -40, -50, -50, -40, -40, -30],
+    [-20, -30, -30, -40, -40, -30, -30, -20],
+    [-10, -20, -20, -20, -20, -20, -20, -10],
+    [ 20,  20,   0,   0,   0,   0,  20,  20],
+    [ 20,  30,  10,   0,   0,  10,  30,  20]
+];
+
+const KING_ENDGAME_SQUARES: [[i32; 8]; 8] = [
+    [-50, -40, -30, -20, -20, -30, -40, -50],
+    [-30, -20, -10,   0,   0, -10, -20, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  30,  40,  40,  30, -10, -30],
+    [-30, -10,  20,  30,  30,  20, -10, -30],
+    [-30, -30,   0,   0,   0,   0, -30, -30],
+    [-50, -30, -30, -30, -30, -30, -30, -50]
+];
+
+/// What a piece is worth in centipawns. The king is never traded, so it
+/// counts for nothing.
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0
+    }
+}
+
+/// Material and piece placement in centipawns, from the point of view of
+/// the side to move: positive when it stands better.
+pub fn evaluate(game_state: &GameState) -> i32 {
+    let bitboards = &game_state.bitboards;
+    let endgame = bitboards.pieces(Color::White, PieceType::Queen) | bitboards.pieces(Color::Black, PieceType::Queen) == EMPTY;
+    let mut score = 0;
+
+    for row in game_state.grid.iter() {
+        for piece in row.iter().flatten() {
+            let table = match piece.piece_type {
+                PieceType::Pawn => &PAWN_SQUARES,
+                PieceType::Knight => &KNIGHT_SQUARES,
+                PieceType::Bishop => &BISHOP_SQUARES,
+                PieceType::Rook => &ROOK_SQUARES,
+                PieceType::Queen => &QUEEN_SQUARES,
+                PieceType::King if endgame => &KING_ENDGAME_SQUARES,
+                PieceType::King => &KING_SQUARES
+            };
+            let (row, sign) = match piece.color {
+                Color::White => (piece.row, 1),
+                Color::Black => (7 - piece.row, -1)
+            };
+            score += sign * (piece_value(piece.piece_type) + table[row][piece.col]);
+        }
+    }
+
+    match game_state.turn {
+        Color::White => score,
+        Color::Black => -score
+    }
+}
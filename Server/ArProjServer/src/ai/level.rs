@@ -0,0 +1,166 @@
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use ai::search::{search, SearchLimits, SearchResult, MATE, MAX_DEPTH};
+use game::gamestate::GameState;
+use game::piece::Move;
+
+/// How hard the computer tries. Every level but `Master` looks only a few
+/// plies ahead, misjudges positions by a random margin and now and then
+/// settles for a move a little worse than its best.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Beginner,
+    Casual,
+    Club,
+    #[default]
+    Master
+}
+
+// how a level plays, short of full strength
+struct Handicap {
+    // plies searched, counting the move itself
+    depth: u32,
+    // each move's score is off by up to this many centipawns either way
+    noise: i32,
+    // the percentage of moves picked at random from those close to the best...
+    slip_chance: u64,
+    // ...where close means at most this many centipawns worse
+    slip_margin: i32
+}
+
+impl Level {
+    fn handicap(self) -> Option<Handicap> {
+        match self {
+            Level::Beginner => Some(Handicap { depth: 2, noise: 120, slip_chance: 30, slip_margin: 300 }),
+            Level::Casual => Some(Handicap { depth: 2, noise: 50, slip_chance: 15, slip_margin: 150 }),
+            Level::Club => Some(Handicap { depth: 3, noise: 15, slip_chance: 5, slip_margin: 60 }),
+            Level::Master => None
+        }
+    }
+}
+
+/// A small pseudo-random generator (splitmix64), seeded by the caller so a
+/// game can be replayed exactly.
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mixed = self.state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        mixed ^ (mixed >> 31)
+    }
+
+    // a number from `-bound` to `bound`, both included
+    fn spread(&mut self, bound: i32) -> i32 {
+        (self.next_u64() % (2 * bound as u64 + 1)) as i32 - bound
+    }
+}
+
+/// Picks the bot's move at `level`. `Master` searches at full strength
+/// until `time` runs out; the other levels score every legal move with a
+/// shallow search, blur the scores and sometimes slip.
+pub fn choose_move(game_state: &GameState, level: Level, time: Duration, rng: &mut Rng) -> SearchResult {
+    // a drawn game can still have legal moves, but none of them may be played
+    if game_state.status.is_over() {
+        return SearchResult { best_move: None, score: 0, depth: 0, nodes: 0 };
+    }
+
+    let stop = AtomicBool::new(false);
+    let handicap = match level.handicap() {
+        Some(handicap) => handicap,
+        None => return search(game_state, SearchLimits { depth: None, time: Some(time) }, &stop)
+    };
+
+    let mut moves = game_state.legal_moves();
+    if moves.is_empty() {
+        return search(game_state, SearchLimits { depth: Some(1), time: None }, &stop);
+    }
+    // legal moves come out of hash sets in no fixed order; the same seed should pick the same move
+    moves.sort_by_key(|candidate| candidate.uci().to_string());
+
+    let mut result = SearchResult { best_move: None, score: -MATE, depth: handicap.depth, nodes: 0 };
+    let mut scored: Vec<(Move, i32)> = Vec::new();
+    for chosen in moves {
+        let mut position = game_state.clone();
+        position.move_piece(chosen.from.row, chosen.from.col, chosen.to.row, chosen.to.col, chosen.promotion()).unwrap();
+
+        let reply = search(&position, SearchLimits { depth: Some(handicap.depth - 1), time: None }, &stop);
+        result.nodes += reply.nodes;
+
+        // mates are never blurred, so the bot still takes one in front of it and sees one coming
+        let score = -reply.score;
+        let noise = if score.abs() >= MATE - MAX_DEPTH as i32 { 0 } else { rng.spread(handicap.noise) };
+        scored.push((chosen, score + noise));
+    }
+
+    let best = scored.iter().map(|&(_, score)| score).max().unwrap();
+
+    let (chosen, score) = if rng.next_u64() % 100 < handicap.slip_chance {
+        let close: Vec<&(Move, i32)> = scored.iter().filter(|&&(_, score)| score >= best - handicap.slip_margin).collect();
+        *close[(rng.next_u64() % close.len() as u64) as usize]
+    } else {
+        *scored.iter().find(|&&(_, score)| score == best).unwrap()
+    };
+
+    result.best_move = Some(chosen);
+    result.score = score;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::piece::PieceType;
+
+    #[test]
+    fn every_level_takes_mate_in_one() {
+        let game_state = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        for &level in [Level::Beginner, Level::Casual, Level::Club].iter() {
+            for seed in 0..20 {
+                let result = choose_move(&game_state, level, Duration::from_secs(1), &mut Rng::new(seed));
+                assert_eq!(result.best_move.unwrap().uci().to_string(), "a1a8", "{:?} with seed {}", level, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn no_move_once_the_game_is_over() {
+        // a draw by the seventy-five-move rule, with moves still on the board
+        let game_state = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 105").unwrap();
+        for &level in [Level::Beginner, Level::Casual, Level::Club, Level::Master].iter() {
+            assert_eq!(choose_move(&game_state, level, Duration::from_secs(1), &mut Rng::new(0)).best_move, None);
+        }
+    }
+
+    #[test]
+    fn a_beginner_varies_its_play_by_seed() {
+        let game_state = GameState::new();
+        let opening = |seed| choose_move(&game_state, Level::Beginner, Duration::from_secs(1), &mut Rng::new(seed)).best_move.unwrap();
+
+        assert_eq!(opening(7), opening(7));
+        assert!((1..10).any(|seed| opening(seed) != opening(0)));
+    }
+
+    #[test]
+    fn a_slip_stays_close_to_the_best_move() {
+        // the queen can take the rook for free; even a beginner never leaves it hanging instead
+        let game_state = GameState::from_fen("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        for seed in 0..30 {
+            let chosen = choose_move(&game_state, Level::Beginner, Duration::from_secs(1), &mut Rng::new(seed)).best_move.unwrap();
+            let mut position = game_state.clone();
+            position.move_piece(chosen.from.row, chosen.from.col, chosen.to.row, chosen.to.col, None).unwrap();
+            assert!(!position.legal_moves().iter().any(|reply| reply.captured.map(|captured| captured.piece_type) == Some(PieceType::Queen)),
+                    "seed {} played {}", seed, chosen.uci());
+        }
+    }
+}
@@ -0,0 +1,6 @@
+//! The computer opponent: a position evaluation and the search that uses
+//! it to pick a move.
+
+pub mod eval;
+pub mod level;
+pub mod search;
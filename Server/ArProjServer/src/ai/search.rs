@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use ai::eval::{evaluate, piece_value};
+use game::gamestate::GameState;
+use game::piece::Move;
+
+/// The score of delivering mate right now. A mate `n` plies away scores
+/// `MATE - n`, so shorter mates are preferred.
+pub const MATE: i32 = 30_000;
+const INFINITY: i32 = MATE + 1;
+
+// no search goes deeper than this, which keeps mate scores apart from ordinary ones
+pub const MAX_DEPTH: u32 = 64;
+
+// how many nodes pass between looks at the clock and the stop flag
+const CHECK_INTERVAL: u64 = 1024;
+
+/// How long a search may run. It ends at whichever limit comes first; with
+/// neither set it runs until `MAX_DEPTH` or until stopped.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>
+}
+
+/// What the deepest finished iteration found.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// Centipawns from the point of view of the side to move; see `MATE`.
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64
+}
+
+/// Finds the best move for the side to move with iterative-deepening
+/// alpha-beta and a quiescence search over captures. Setting `stop` ends
+/// the search early with the best move found so far.
+pub fn search(game_state: &GameState, limits: SearchLimits, stop: &AtomicBool) -> SearchResult {
+    let mut searcher = Searcher {
+        position: game_state.clone(),
+        // positions before the last capture or pawn move can never come back
+        seen: game_state.position_history.iter().rev().take(game_state.halfmove_clock as usize + 1).rev().cloned().collect(),
+        best_moves: HashMap::new(),
+        deadline: limits.time.map(|time| Instant::now() + time),
+        stop,
+        stopped: false,
+        nodes: 0
+    };
+
+    let legal_moves = game_state.legal_moves();
+    let mut result = SearchResult {
+        best_move: order_moves(legal_moves, None).first().cloned(),
+        score: 0,
+        depth: 0,
+        nodes: 0
+    };
+    if result.best_move.is_none() {
+        result.score = if game_state.is_in_check(game_state.turn) { -MATE } else { 0 };
+        return result;
+    }
+
+    let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+    for depth in 1..=max_depth {
+        let score = searcher.alpha_beta(depth, 0, -INFINITY, INFINITY);
+        if searcher.stopped {
+            break;
+        }
+
+        result = SearchResult {
+            best_move: searcher.best_moves.get(&game_state.hash).cloned().or(result.best_move),
+            score,
+            depth,
+            nodes: searcher.nodes
+        };
+
+        // nothing deeper can beat a forced mate
+        if score.abs() >= MATE - MAX_DEPTH as i32 {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result
+}
+
+struct Searcher<'a> {
+    // made and unmade along the current line
+    position: GameState,
+    // hashes of the positions leading up to the current one, for spotting repetitions
+    seen: Vec<u64>,
+    // the best move found in each position, tried first the next time round
+    best_moves: HashMap<u64, Move>,
+    deadline: Option<Instant>,
+    stop: &'a AtomicBool,
+    stopped: bool,
+    nodes: u64
+}
+
+impl<'a> Searcher<'a> {
+    fn alpha_beta(&mut self, depth: u32, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+
+        // a repetition is scored as a draw the first time round, which is all the search needs
+        if ply > 0 && (self.position.halfmove_clock >= 100 || self.is_repetition()) {
+            return 0;
+        }
+
+        let moves = self.position.legal_moves();
+        if moves.is_empty() {
+            return if self.position.is_in_check(self.position.turn) { -MATE + ply as i32 } else { 0 };
+        }
+        if depth == 0 {
+            return self.quiescence(alpha, beta);
+        }
+
+        let hash = self.position.hash;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for chosen in order_moves(moves, self.best_moves.get(&hash).cloned()) {
+            let score = -self.play(chosen, |searcher| searcher.alpha_beta(depth - 1, ply + 1, -beta, -alpha));
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(chosen);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if let Some(best_move) = best_move {
+            self.best_moves.insert(hash, best_move);
+        }
+        best_score
+    }
+
+    // only captures and promotions are searched, until the position is quiet
+    fn quiescence(&mut self, mut alpha: i32, beta: i32) -> i32 {
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let standing = evaluate(&self.position);
+        if standing >= beta {
+            return standing;
+        }
+        if standing > alpha {
+            alpha = standing;
+        }
+
+        let noisy: Vec<Move> = self.position.legal_moves().into_iter()
+            .filter(|candidate| candidate.is_capture() || candidate.promotion().is_some())
+            .collect();
+        for chosen in order_moves(noisy, None) {
+            let score = -self.play(chosen, |searcher| searcher.quiescence(-beta, -alpha));
+            if self.stopped {
+                return 0;
+            }
+
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        alpha
+    }
+
+    // searches the position after `chosen` and takes the move back again
+    fn play<F: FnOnce(&mut Searcher<'a>) -> i32>(&mut self, chosen: Move, search: F) -> i32 {
+        let undo = self.position.make_move(chosen);
+        self.seen.push(self.position.hash);
+
+        let score = search(self);
+
+        self.seen.pop();
+        self.position.unmake_move(undo);
+        score
+    }
+
+    fn is_repetition(&self) -> bool {
+        let hash = self.position.hash;
+        let earlier = &self.seen[..self.seen.len() - 1];
+        earlier.iter().rev().take(self.position.halfmove_clock as usize).any(|&seen| seen == hash)
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if !self.stopped && self.nodes.is_multiple_of(CHECK_INTERVAL) {
+            self.stopped = self.stop.load(Ordering::Relaxed) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+}
+
+// the remembered best move first, then captures of the most valuable piece by the least
+// valuable one, then promotions, then everything else
+fn order_moves(mut moves: Vec<Move>, best_move: Option<Move>) -> Vec<Move> {
+    moves.sort_by_key(|candidate| {
+        if Some(*candidate) == best_move {
+            return i32::MIN;
+        }
+
+        let capture = candidate.captured.map_or(0, |captured| 10 * piece_value(captured.piece_type) - piece_value(candidate.piece.piece_type) + 10_000);
+        let promotion = candidate.promotion().map_or(0, piece_value);
+        -(capture + promotion)
+    });
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_move(fen: &str, depth: u32) -> SearchResult {
+        let game_state = GameState::from_fen(fen).unwrap();
+        search(&game_state, SearchLimits { depth: Some(depth), time: None }, &AtomicBool::new(false))
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+
+        assert_eq!(result.best_move.unwrap().uci().to_string(), "a1a8");
+        assert_eq!(result.score, MATE - 1);
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let result = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert_eq!(result.best_move.unwrap().uci().to_string(), "d2d5");
+    }
+
+    #[test]
+    fn sees_a_defended_piece_is_no_gift() {
+        // the pawn is defended, so Qxe5 loses the queen for it
+        let result = best_move("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", 2);
+        assert_ne!(result.best_move.unwrap().uci().to_string(), "e1e5");
+    }
+
+    #[test]
+    fn no_move_without_legal_moves() {
+        let stalemate = best_move("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(stalemate.best_move, None);
+        assert_eq!(stalemate.score, 0);
+
+        let mated = best_move("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 3);
+        assert_eq!(mated.best_move, None);
+        assert_eq!(mated.score, -MATE);
+    }
+
+    #[test]
+    fn stopping_still_leaves_a_legal_move() {
+        let game_state = GameState::new();
+        let result = search(&game_state, SearchLimits::default(), &AtomicBool::new(true));
+
+        assert!(game_state.legal_moves().contains(&result.best_move.unwrap()));
+    }
+}
@@ -0,0 +1,129 @@
+use game::bitboard::{square_index, Bitboard, Bitboards, EMPTY};
+use game::gamestate::GameState;
+use game::piece::Color;
+use game::piece::Coord;
+use game::piece::PieceType;
+
+const KNIGHT_JUMPS: [(isize, isize); 8] = [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)];
+const KING_STEPS: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+const WHITE_PAWN_CAPTURES: [(isize, isize); 2] = [(-1, -1), (-1, 1)];
+const BLACK_PAWN_CAPTURES: [(isize, isize); 2] = [(1, -1), (1, 1)];
+
+// the first four lines run towards a8 (lower bit indices), the last four towards h1
+const LINES: [(isize, isize); 8] = [(-1, 0), (0, -1), (-1, -1), (-1, 1), (1, 0), (0, 1), (1, 1), (1, -1)];
+const STRAIGHT_LINES: [usize; 4] = [0, 1, 4, 5];
+const DIAGONAL_LINES: [usize; 4] = [2, 3, 6, 7];
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = step_table(&KNIGHT_JUMPS);
+const KING_ATTACKS: [Bitboard; 64] = step_table(&KING_STEPS);
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_table(&WHITE_PAWN_CAPTURES), step_table(&BLACK_PAWN_CAPTURES)];
+const RAYS: [[Bitboard; 64]; 8] = ray_tables();
+
+// every square one of the steps reaches from each square, worked out at compile time
+const fn step_table(steps: &[(isize, isize)]) -> [Bitboard; 64] {
+    let mut table = [EMPTY; 64];
+    let mut index = 0;
+    while index < 64 {
+        let mut step = 0;
+        while step < steps.len() {
+            let row = (index / 8) as isize + steps[step].0;
+            let col = (index % 8) as isize + steps[step].1;
+            if row >= 0 && row < 8 && col >= 0 && col < 8 {
+                table[index] |= 1 << (row * 8 + col);
+            }
+            step += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+// every square along each line from each square, up to the edge of the board
+const fn ray_tables() -> [[Bitboard; 64]; 8] {
+    let mut tables = [[EMPTY; 64]; 8];
+    let mut line = 0;
+    while line < 8 {
+        let mut index = 0;
+        while index < 64 {
+            let mut row = (index / 8) as isize + LINES[line].0;
+            let mut col = (index % 8) as isize + LINES[line].1;
+            while row >= 0 && row < 8 && col >= 0 && col < 8 {
+                tables[line][index] |= 1 << (row * 8 + col);
+                row += LINES[line].0;
+                col += LINES[line].1;
+            }
+            index += 1;
+        }
+        line += 1;
+    }
+    tables
+}
+
+// the squares along a line up to and including the first piece in the way
+fn ray_attacks(line: usize, index: usize, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[line][index];
+    let blockers = ray & occupied;
+    if blockers == EMPTY {
+        return ray;
+    }
+
+    let nearest = if line < 4 { 63 - blockers.leading_zeros() } else { blockers.trailing_zeros() };
+    ray ^ RAYS[line][nearest as usize]
+}
+
+pub fn knight_attacks(index: usize) -> Bitboard {
+    KNIGHT_ATTACKS[index]
+}
+
+pub fn king_attacks(index: usize) -> Bitboard {
+    KING_ATTACKS[index]
+}
+
+/// The two squares diagonally in front of a pawn of `color`.
+pub fn pawn_attacks(color: Color, index: usize) -> Bitboard {
+    PAWN_ATTACKS[color as usize][index]
+}
+
+pub fn rook_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    STRAIGHT_LINES.iter().fold(EMPTY, |attacks, &line| attacks | ray_attacks(line, index, occupied))
+}
+
+pub fn bishop_attacks(index: usize, occupied: Bitboard) -> Bitboard {
+    DIAGONAL_LINES.iter().fold(EMPTY, |attacks, &line| attacks | ray_attacks(line, index, occupied))
+}
+
+/// Whether any piece of `by_color` on `bitboards` attacks the square.
+pub fn attacked_on(bitboards: &Bitboards, index: usize, by_color: Color) -> bool {
+    let occupied = bitboards.occupied();
+    let straight = bitboards.pieces(by_color, PieceType::Rook) | bitboards.pieces(by_color, PieceType::Queen);
+    let diagonal = bitboards.pieces(by_color, PieceType::Bishop) | bitboards.pieces(by_color, PieceType::Queen);
+
+    // a pawn of the other color on the square would capture the pawns attacking it
+    pawn_attacks(by_color.opponent(), index) & bitboards.pieces(by_color, PieceType::Pawn) != EMPTY
+        || knight_attacks(index) & bitboards.pieces(by_color, PieceType::Knight) != EMPTY
+        || king_attacks(index) & bitboards.pieces(by_color, PieceType::King) != EMPTY
+        || rook_attacks(index, occupied) & straight != EMPTY
+        || bishop_attacks(index, occupied) & diagonal != EMPTY
+}
+
+/// Whether any piece of `by_color` attacks `target`: could capture a piece
+/// standing there, whether or not that capture would be legal. Pinned
+/// pieces and kings attack like any other piece, and pawns attack only
+/// diagonally forward.
+pub fn is_square_attacked(game_state: &GameState, target: Coord, by_color: Color) -> bool {
+    attacked_on(&game_state.bitboards, square_index(target.row, target.col), by_color)
+}
+
+/// Every square `by_color` attacks.
+pub fn attacked_squares(game_state: &GameState, by_color: Color) -> Vec<Coord> {
+    let mut squares = Vec::new();
+    for row in 0..game_state.rows {
+        for col in 0..game_state.cols {
+            if attacked_on(&game_state.bitboards, square_index(row, col), by_color) {
+                squares.push(Coord { row, col });
+            }
+        }
+    }
+    squares
+}
+
@@ -0,0 +1,106 @@
+use game::piece::Color;
+use game::piece::Coord;
+use game::piece::Move;
+use game::piece::Piece;
+use game::piece::PieceType;
+
+/// A set of squares, one bit each. Bit `row * 8 + col` is the square at
+/// `grid[row][col]`, so bit 0 is a8 and bit 63 is h1.
+pub type Bitboard = u64;
+
+pub const EMPTY: Bitboard = 0;
+
+pub fn square_index(row: usize, col: usize) -> usize {
+    row * 8 + col
+}
+
+pub fn square_bit(row: usize, col: usize) -> Bitboard {
+    1 << square_index(row, col)
+}
+
+pub fn index_coord(index: usize) -> Coord {
+    Coord { row: index / 8, col: index % 8 }
+}
+
+/// Walks the squares of a bitboard from a8 to h1, as indices.
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == EMPTY {
+            return None;
+        }
+
+        let index = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+}
+
+pub fn squares(bitboard: Bitboard) -> Squares {
+    Squares(bitboard)
+}
+
+/// The position as one bitboard per color and piece type. `GameState` keeps
+/// this next to the grid: the grid says what stands on a square, the
+/// bitboards where a kind of piece stands and what it attacks.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Default)]
+pub struct Bitboards {
+    pieces: [[Bitboard; 6]; 2],
+    colors: [Bitboard; 2]
+}
+
+impl Bitboards {
+    pub fn from_grid(grid: &[[Option<Piece>; 8]; 8]) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+        for row in grid.iter() {
+            for piece in row.iter().flatten() {
+                bitboards.toggle(piece.color, piece.piece_type, square_bit(piece.row, piece.col));
+            }
+        }
+        bitboards
+    }
+
+    pub fn pieces(&self, color: Color, piece_type: PieceType) -> Bitboard {
+        self.pieces[color as usize][piece_type as usize]
+    }
+
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<usize> {
+        squares(self.pieces(color, PieceType::King)).next()
+    }
+
+    /// Plays a move produced by the generators, the same way `make_move`
+    /// does on the grid. Applying the same move again takes it back.
+    pub fn apply(&mut self, chosen: &Move) {
+        let piece = chosen.piece;
+
+        if let Some(captured) = chosen.captured {
+            // for en passant this is the pawn beside `from`, not on `to`
+            self.toggle(captured.color, captured.piece_type, square_bit(captured.row, captured.col));
+        }
+
+        self.toggle(piece.color, piece.piece_type, square_bit(chosen.from.row, chosen.from.col));
+        let landing = chosen.promotion().unwrap_or(piece.piece_type);
+        self.toggle(piece.color, landing, square_bit(chosen.to.row, chosen.to.col));
+
+        if let Some((rook_from, rook_to)) = chosen.rook_hop() {
+            self.toggle(piece.color, PieceType::Rook, square_bit(rook_from.row, rook_from.col) | square_bit(rook_to.row, rook_to.col));
+        }
+    }
+
+    // adds the squares that are empty and removes the ones that are not
+    fn toggle(&mut self, color: Color, piece_type: PieceType, bits: Bitboard) {
+        self.pieces[color as usize][piece_type as usize] ^= bits;
+        self.colors[color as usize] ^= bits;
+    }
+}
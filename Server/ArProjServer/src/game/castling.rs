@@ -0,0 +1,82 @@
+use game::piece::Color;
+use game::piece::Coord;
+use game::piece::MoveKind;
+
+// castling letter, the side it belongs to, the king's row and the column its rook starts on
+pub const CASTLING_CORNERS: [(char, Color, MoveKind, usize, usize); 4] = [
+    ('K', Color::White, MoveKind::CastleKingside, 7, 7),
+    ('Q', Color::White, MoveKind::CastleQueenside, 7, 0),
+    ('k', Color::Black, MoveKind::CastleKingside, 0, 7),
+    ('q', Color::Black, MoveKind::CastleQueenside, 0, 0)
+];
+
+/// Which castles each side may still play at some point in the game. A
+/// right is lost for good once its king or rook leaves home or anything
+/// lands on the rook's corner, so a rook that is captured and replaced by
+/// another does not bring it back. Whether the castle can be played right
+/// now is up to the move generator.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Default)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool
+}
+
+impl CastlingRights {
+    pub fn all() -> CastlingRights {
+        CastlingRights { white_kingside: true, white_queenside: true, black_kingside: true, black_queenside: true }
+    }
+
+    pub fn none() -> CastlingRights {
+        CastlingRights::default()
+    }
+
+    /// Whether `color` keeps the right to castle on the side of `kind`,
+    /// which is `CastleKingside` or `CastleQueenside`.
+    pub fn allows(&self, color: Color, kind: MoveKind) -> bool {
+        match (color, kind) {
+            (Color::White, MoveKind::CastleKingside) => self.white_kingside,
+            (Color::White, MoveKind::CastleQueenside) => self.white_queenside,
+            (Color::Black, MoveKind::CastleKingside) => self.black_kingside,
+            (Color::Black, MoveKind::CastleQueenside) => self.black_queenside,
+            _ => false
+        }
+    }
+
+    pub fn set(&mut self, color: Color, kind: MoveKind, allowed: bool) {
+        match (color, kind) {
+            (Color::White, MoveKind::CastleKingside) => self.white_kingside = allowed,
+            (Color::White, MoveKind::CastleQueenside) => self.white_queenside = allowed,
+            (Color::Black, MoveKind::CastleKingside) => self.black_kingside = allowed,
+            (Color::Black, MoveKind::CastleQueenside) => self.black_queenside = allowed,
+            _ => ()
+        }
+    }
+
+    /// Drops every right whose king or rook starts on `square`. Called with
+    /// both ends of every move played.
+    pub fn touch(&mut self, square: Coord) {
+        for &(_, color, kind, row, rook_col) in CASTLING_CORNERS.iter() {
+            if square.row == row && (square.col == 4 || square.col == rook_col) {
+                self.set(color, kind, false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::gamestate::GameState;
+    use game::perft::test_support::play;
+
+    #[test]
+    fn a_replaced_rook_cannot_castle() {
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/7R/6b1/R3K2R b KQ - 0 1").unwrap();
+        play(&mut game_state, "g2h1 h3h1 e8e7");
+
+        assert!(game_state.to_fen().contains(" w Q "));
+        assert!(game_state.legal_moves().iter().all(|chosen| chosen.uci().to_string() != "e1g1"));
+        assert!(game_state.legal_moves().iter().any(|chosen| chosen.uci().to_string() == "e1c1"));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt;
+
+use game::gamestate::GameState;
+use game::piece::PieceType;
+
+// halfmove counts at which the fifty and seventy-five move rules kick in
+pub const FIFTY_MOVE_LIMIT: u32 = 100;
+pub const SEVENTY_FIVE_MOVE_LIMIT: u32 = 150;
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawReason {
+    /// Claimed: the position has occurred three times.
+    ThreefoldRepetition,
+    /// Automatic: the position has occurred five times.
+    FivefoldRepetition,
+    /// Claimed: fifty moves each without a capture or pawn move.
+    FiftyMoveRule,
+    /// Automatic: seventy-five moves each without a capture or pawn move.
+    SeventyFiveMoveRule,
+    /// Automatic: neither side has the material left to checkmate.
+    InsufficientMaterial
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match *self {
+            DrawReason::ThreefoldRepetition => "threefold repetition",
+            DrawReason::FivefoldRepetition => "fivefold repetition",
+            DrawReason::FiftyMoveRule => "the fifty-move rule",
+            DrawReason::SeventyFiveMoveRule => "the seventy-five-move rule",
+            DrawReason::InsufficientMaterial => "insufficient material"
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// How many times the current position has occurred, including now.
+pub fn repetition_count(game_state: &GameState) -> usize {
+    match game_state.position_history.last() {
+        Some(current) => game_state.position_history.iter().filter(|key| *key == current).count(),
+        None => 0
+    }
+}
+
+/// Dead positions: king against king, king and a single minor piece against
+/// king, or nothing but kings and bishops that all stand on one square color.
+pub fn insufficient_material(game_state: &GameState) -> bool {
+    let mut minors = Vec::new();
+
+    for piece in game_state.piece_list() {
+        match piece.piece_type {
+            PieceType::King => (),
+            PieceType::Bishop | PieceType::Knight => minors.push(piece),
+            _ => return false
+        }
+    }
+
+    match minors.len() {
+        0 | 1 => true,
+        _ => {
+            let square_color = |row: usize, col: usize| (row + col) % 2;
+            let first = square_color(minors[0].row, minors[0].col);
+            minors.iter().all(|piece| piece.piece_type == PieceType::Bishop && square_color(piece.row, piece.col) == first)
+        }
+    }
+}
+
+/// A draw that ends the game without anyone asking for it.
+pub fn automatic_draw(game_state: &GameState) -> Option<DrawReason> {
+    if insufficient_material(game_state) {
+        Some(DrawReason::InsufficientMaterial)
+    } else if repetition_count(game_state) >= 5 {
+        Some(DrawReason::FivefoldRepetition)
+    } else if game_state.halfmove_clock >= SEVENTY_FIVE_MOVE_LIMIT {
+        Some(DrawReason::SeventyFiveMoveRule)
+    } else {
+        None
+    }
+}
+
+/// A draw the side to move may claim right now.
+pub fn claimable_draw(game_state: &GameState) -> Option<DrawReason> {
+    if repetition_count(game_state) >= 3 {
+        Some(DrawReason::ThreefoldRepetition)
+    } else if game_state.halfmove_clock >= FIFTY_MOVE_LIMIT {
+        Some(DrawReason::FiftyMoveRule)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::gamestate::GameStatus;
+    use game::perft::test_support::play;
+
+    // the knights go out and come back, bringing the start position round again
+    const KNIGHT_SHUFFLE: &str = "g1f3 g8f6 f3g1 f6g8";
+
+    #[test]
+    fn threefold_repetition_may_be_claimed() {
+        let mut game_state = GameState::new();
+        play(&mut game_state, KNIGHT_SHUFFLE);
+        assert_eq!(game_state.claimable_draw(), None);
+
+        play(&mut game_state, KNIGHT_SHUFFLE);
+        assert_eq!(repetition_count(&game_state), 3);
+        assert_eq!(game_state.claimable_draw(), Some(DrawReason::ThreefoldRepetition));
+        assert_eq!(game_state.status, GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn fivefold_repetition_ends_the_game() {
+        let mut game_state = GameState::new();
+        for _ in 0..4 {
+            play(&mut game_state, KNIGHT_SHUFFLE);
+        }
+
+        assert_eq!(repetition_count(&game_state), 5);
+        assert_eq!(game_state.status, GameStatus::Draw { reason: DrawReason::FivefoldRepetition });
+    }
+
+    #[test]
+    fn long_quiet_stretches_draw() {
+        let fifty = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert_eq!(fifty.claimable_draw(), Some(DrawReason::FiftyMoveRule));
+        assert_eq!(fifty.status, GameStatus::Ongoing);
+
+        let seventy_five = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 150 105").unwrap();
+        assert_eq!(seventy_five.status, GameStatus::Draw { reason: DrawReason::SeventyFiveMoveRule });
+
+        let not_yet = GameState::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(not_yet.claimable_draw(), None);
+    }
+
+    #[test]
+    fn only_dead_positions_lack_material() {
+        let dead = |fen: &str| insufficient_material(&GameState::from_fen(fen).ok().unwrap());
+
+        assert!(dead("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(dead("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        // c1 and d6 are both dark squares
+        assert!(dead("4k3/8/3b4/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!dead("4k3/8/3b4/8/8/8/8/4KB2 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1"));
+        assert!(!dead("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+    }
+}
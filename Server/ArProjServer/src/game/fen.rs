@@ -0,0 +1,316 @@
+use std::error::Error;
+use std::fmt;
+
+use game::bitboard::Bitboards;
+use game::castling::{CastlingRights, CASTLING_CORNERS};
+use game::gamestate::GameState;
+use game::piece::Color;
+use game::piece::Coord;
+use game::piece::Piece;
+use game::piece::PieceType;
+use game::zobrist::position_hash;
+
+/// The standard starting position.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Why a FEN string could not be read. Fields are counted from 1 as they
+/// appear in the string: placement, side to move, castling, en passant,
+/// halfmove clock, fullmove number.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum FenError {
+    /// Placement, side to move, castling and en passant are required; the clocks may be left off.
+    WrongFieldCount(usize),
+    /// The placement does not have eight ranks separated by `/`.
+    WrongRankCount(usize),
+    /// A rank (8 down to 1) does not add up to eight squares.
+    BadRankLength(usize),
+    /// A character in the placement that is neither a piece letter nor a digit 1-8.
+    UnknownPiece(char),
+    /// A pawn standing on the first or last rank.
+    PawnOnBackRank,
+    /// Each side needs exactly one king; holds the side that does not have one.
+    KingCount(Color),
+    /// The side that just moved left its own king in check, so the side to move could capture it.
+    OpponentInCheck,
+    /// The side to move is not `w` or `b`.
+    BadSideToMove(String),
+    /// The castling field is not `-` or some of `KQkq`, or names a right whose king or rook is not home.
+    BadCastling(String),
+    /// The en passant field is not `-` or a square on the sixth (or third) rank behind a pawn that just pushed.
+    BadEnPassant(String),
+    /// A clock that is not a non-negative number.
+    BadClock(String)
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FenError::WrongFieldCount(count) => write!(f, "Expected 4 to 6 space separated fields, found {}", count),
+            FenError::WrongRankCount(count) => write!(f, "Expected 8 ranks in the piece placement, found {}", count),
+            FenError::BadRankLength(rank) => write!(f, "Rank {} does not have exactly 8 squares", rank),
+            FenError::UnknownPiece(letter) => write!(f, "Unknown piece letter '{}'", letter),
+            FenError::PawnOnBackRank => write!(f, "Pawns cannot stand on the first or last rank"),
+            FenError::KingCount(color) => write!(f, "{:?} must have exactly one king", color),
+            FenError::OpponentInCheck => write!(f, "The side not to move cannot be in check"),
+            FenError::BadSideToMove(ref field) => write!(f, "Side to move must be 'w' or 'b', found '{}'", field),
+            FenError::BadCastling(ref field) => write!(f, "Invalid castling rights '{}'", field),
+            FenError::BadEnPassant(ref field) => write!(f, "Invalid en passant square '{}'", field),
+            FenError::BadClock(ref field) => write!(f, "Invalid move clock '{}'", field)
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// Builds a position from Forsyth-Edwards Notation.
+pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 || fields.len() > 6 {
+        return Err(FenError::WrongFieldCount(fields.len()));
+    }
+
+    let mut game_state = GameState::new();
+    game_state.grid = parse_placement(fields[0])?;
+    game_state.bitboards = Bitboards::from_grid(&game_state.grid);
+
+    game_state.turn = match fields[1] {
+        "w" => Color::White,
+        "b" => Color::Black,
+        other => return Err(FenError::BadSideToMove(other.to_string()))
+    };
+    if game_state.is_in_check(game_state.turn.opponent()) {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    parse_castling(&mut game_state, fields[2])?;
+    game_state.en_passant = parse_en_passant(&game_state, fields[3])?;
+
+    game_state.halfmove_clock = match fields.get(4) {
+        Some(field) => field.parse().map_err(|_| FenError::BadClock(field.to_string()))?,
+        None => 0
+    };
+    game_state.fullmove_number = match fields.get(5) {
+        Some(field) => match field.parse() {
+            Ok(number) if number > 0 => number,
+            _ => return Err(FenError::BadClock(field.to_string()))
+        },
+        None => 1
+    };
+
+    game_state.initial_fen = to_fen(&game_state);
+    game_state.hash = position_hash(&game_state);
+    game_state.position_history = vec![game_state.hash];
+    game_state.status = game_state.evaluate_status();
+    Ok(game_state)
+}
+
+/// Writes the position out as Forsyth-Edwards Notation.
+pub fn to_fen(game_state: &GameState) -> String {
+    let mut fen = String::new();
+
+    for row in 0..game_state.rows {
+        let mut empty = 0;
+        for col in 0..game_state.cols {
+            match game_state.grid[row][col] {
+                Some(piece) => {
+                    if empty > 0 {
+                        fen.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    fen.push(piece_letter(piece));
+                },
+                None => empty += 1
+            }
+        }
+        if empty > 0 {
+            fen.push_str(&empty.to_string());
+        }
+        if row + 1 < game_state.rows {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(match game_state.turn {
+        Color::White => " w ",
+        Color::Black => " b "
+    });
+
+    let mut castling = String::new();
+    for &(letter, color, kind, _, _) in CASTLING_CORNERS.iter() {
+        if game_state.castling.allows(color, kind) {
+            castling.push(letter);
+        }
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    fen.push_str(&castling);
+
+    fen.push(' ');
+    match game_state.en_passant {
+        Some(square) => fen.push_str(&square.square()),
+        None => fen.push('-')
+    }
+
+    fen.push_str(&format!(" {} {}", game_state.halfmove_clock, game_state.fullmove_number));
+    fen
+}
+
+fn parse_placement(field: &str) -> Result<[[Option<Piece>; 8]; 8], FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut grid = [[None; 8]; 8];
+    let mut kings = [0, 0];
+
+    for (row, rank) in ranks.iter().enumerate() {
+        let mut col = 0;
+        for letter in rank.chars() {
+            if let Some(skip) = letter.to_digit(10) {
+                if skip == 0 || skip > 8 {
+                    return Err(FenError::UnknownPiece(letter));
+                }
+                col += skip as usize;
+                continue;
+            }
+
+            let piece_type = PieceType::from_letter(letter).ok_or(FenError::UnknownPiece(letter))?;
+            let color = if letter.is_ascii_uppercase() { Color::White } else { Color::Black };
+            if col >= 8 {
+                return Err(FenError::BadRankLength(8 - row));
+            }
+
+            match piece_type {
+                PieceType::Pawn if row == 0 || row == 7 => return Err(FenError::PawnOnBackRank),
+                PieceType::King => kings[color as usize] += 1,
+                _ => ()
+            }
+
+            grid[row][col] = Some(Piece { piece_type, row, col, color });
+            col += 1;
+        }
+
+        if col != 8 {
+            return Err(FenError::BadRankLength(8 - row));
+        }
+    }
+
+    for &color in [Color::White, Color::Black].iter() {
+        if kings[color as usize] != 1 {
+            return Err(FenError::KingCount(color));
+        }
+    }
+
+    Ok(grid)
+}
+
+// the castling rights, each of which needs its king and rook at home
+fn parse_castling(game_state: &mut GameState, field: &str) -> Result<(), FenError> {
+    game_state.castling = CastlingRights::none();
+    if field == "-" {
+        return Ok(());
+    }
+
+    let bad_castling = || FenError::BadCastling(field.to_string());
+    for letter in field.chars() {
+        let &(_, color, kind, row, rook_col) = CASTLING_CORNERS.iter().find(|corner| corner.0 == letter).ok_or_else(bad_castling)?;
+
+        for &(col, piece_type) in [(4, PieceType::King), (rook_col, PieceType::Rook)].iter() {
+            match game_state.grid[row][col] {
+                Some(piece) if piece.piece_type == piece_type && piece.color == color => (),
+                _ => return Err(bad_castling())
+            }
+        }
+        game_state.castling.set(color, kind, true);
+    }
+
+    Ok(())
+}
+
+// the square behind a pawn that has just made a double push
+fn parse_en_passant(game_state: &GameState, field: &str) -> Result<Option<Coord>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+
+    let bad_en_passant = || FenError::BadEnPassant(field.to_string());
+    let target = Coord::from_square(field).ok_or_else(bad_en_passant)?;
+
+    // the pusher belongs to the side that just moved
+    let (expected_row, from_row, to_row) = match game_state.turn {
+        Color::White => (2, 1, 3),
+        Color::Black => (5, 6, 4)
+    };
+    if target.row != expected_row || game_state.grid[target.row][target.col].is_some() || game_state.grid[from_row][target.col].is_some() {
+        return Err(bad_en_passant());
+    }
+
+    match game_state.grid[to_row][target.col] {
+        Some(pawn) if pawn.piece_type == PieceType::Pawn && pawn.color != game_state.turn => Ok(Some(target)),
+        _ => Err(bad_en_passant())
+    }
+}
+
+fn piece_letter(piece: Piece) -> char {
+    match piece.color {
+        Color::White => piece.piece_type.letter(),
+        Color::Black => piece.piece_type.letter().to_ascii_lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_fen_round_trips() {
+        for fen in [START_FEN,
+                    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+                    "8/8/4k3/8/8/4K3/8/8 b - - 37 80"].iter() {
+            assert_eq!(to_fen(&from_fen(fen).unwrap()), *fen);
+        }
+    }
+
+    #[test]
+    fn the_clocks_may_be_left_off() {
+        let read_back = |fen| to_fen(&from_fen(fen).ok().unwrap());
+        assert_eq!(read_back("8/8/4k3/8/8/4K3/8/8 w - -"), "8/8/4k3/8/8/4K3/8/8 w - - 0 1");
+        assert_eq!(read_back("8/8/4k3/8/8/4K3/8/8 w - - 12"), "8/8/4k3/8/8/4K3/8/8 w - - 12 1");
+    }
+
+    #[test]
+    fn each_mistake_has_its_own_error() {
+        let cases = [
+            ("8/8/4k3/8/8/4K3/8/8 w", FenError::WrongFieldCount(2)),
+            ("8/8/4k3/8/8/4K3/8/8 w - - 0 1 extra", FenError::WrongFieldCount(7)),
+            ("8/8/4k3/8/8/4K3/8 w - - 0 1", FenError::WrongRankCount(7)),
+            ("8/8/4k3/8/8/4K3/8/7 w - - 0 1", FenError::BadRankLength(1)),
+            ("8/8/4k4/8/8/4K3/8/8 w - - 0 1", FenError::BadRankLength(6)),
+            ("8/8/4k3/8/8/4K3/8/7X w - - 0 1", FenError::UnknownPiece('X')),
+            ("8/8/4k3/8/8/4K3/8/9 w - - 0 1", FenError::UnknownPiece('9')),
+            ("P7/8/4k3/8/8/4K3/8/8 w - - 0 1", FenError::PawnOnBackRank),
+            ("8/8/8/8/8/4K3/8/8 w - - 0 1", FenError::KingCount(Color::Black)),
+            ("8/8/4k3/8/8/4K3/8/4K3 w - - 0 1", FenError::KingCount(Color::White)),
+            ("4k3/4Q3/8/8/8/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+            ("8/8/4k3/8/8/4K3/8/8 x - - 0 1", FenError::BadSideToMove("x".to_string())),
+            ("8/8/4k3/8/8/4K3/8/8 w KX - 0 1", FenError::BadCastling("KX".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", FenError::BadCastling("K".to_string())),
+            ("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1", FenError::BadEnPassant("e3".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 b - e3 0 1", FenError::BadEnPassant("e3".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w - - x 1", FenError::BadClock("x".to_string())),
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 0", FenError::BadClock("0".to_string()))
+        ];
+        for &(fen, ref error) in cases.iter() {
+            assert_eq!(from_fen(fen).err().as_ref(), Some(error), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn the_side_not_to_move_cannot_be_in_check() {
+        assert_eq!(from_fen("4k3/4Q3/8/8/8/8/8/4K3 w - - 0 1").err(), Some(FenError::OpponentInCheck));
+        assert!(from_fen("4k3/4Q3/8/8/8/8/8/4K3 b - - 0 1").is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+use std::error::Error;
 use std::fmt;
 use std::collections::HashSet;
 
@@ -5,47 +6,73 @@ use game::piece::Piece;
 use game::piece::Color;
 use game::piece::Coord;
 use game::piece::PieceType;
+use game::piece::Move;
+use game::piece::MoveKind;
+use game::attack::{attacked_on, attacked_squares, is_square_attacked};
+use game::bitboard::{index_coord, square_bit, squares, Bitboard, Bitboards, EMPTY};
+use game::castling::CastlingRights;
+use game::draw::{automatic_draw, claimable_draw, DrawReason};
+use game::fen::{from_fen, to_fen, FenError, START_FEN};
+use game::perft::{divide, perft};
+use game::zobrist::{move_hash, position_hash, state_hash};
 use game::king::possible_king_moves;
-use game::king::in_check;
 use game::pawn::possible_pawn_moves;
 use game::rook::possible_rook_moves;
 use game::bishop::possible_bishop_moves;
 use game::knight::possible_knight_moves;
 use game::queen::possible_queen_moves;
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct GameState {
     pub grid: [[Option<Piece>; 8]; 8],
+    // the grid again, one bitboard per color and piece type, for move generation and attacks
+    pub bitboards: Bitboards,
     pub rows: usize,
     pub cols: usize,
-    pub last_move: (Option<Piece>, Option<Coord>),
+    pub last_move: Option<Move>,
+    // every move played through move_piece, in order
+    pub moves: Vec<Move>,
+    // where moves starts from, for replaying the game
+    pub initial_fen: String,
     pub turn: Color,
-    pub checking_state: bool
+    // the castles each side has not yet given up
+    pub castling: CastlingRights,
+    // the square a pawn skipped with a double push on the last move, where it can be taken en passant
+    pub en_passant: Option<Coord>,
+    pub status: GameStatus,
+    // halfmoves since the last capture or pawn move
+    pub halfmove_clock: u32,
+    // starts at 1 and goes up after each of Black's moves
+    pub fullmove_number: u32,
+    // Zobrist key of the position, kept up to date by make_move
+    pub hash: u64,
+    // the hash of every position reached so far, the current one last
+    pub position_history: Vec<u64>
 }
 
 impl GameState {
     pub fn new() -> GameState {
-        GameState {
+        let mut game_state = GameState {
             grid: [
                 [ // Rc::new((Some(piece))
-                    Some(Piece { piece_type: PieceType::Rook, has_moved: false, row: 0, col: 0, color: Color::Black}), 
-                    Some(Piece { piece_type: PieceType::Knight, has_moved: false, row: 0, col: 1, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Bishop, has_moved: false, row: 0, col: 2, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Queen, has_moved: false, row: 0, col: 3, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::King, has_moved: false, row: 0, col: 4, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Bishop, has_moved: false, row: 0, col: 5, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Knight, has_moved: false, row: 0, col: 6, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Rook, has_moved: false, row: 0, col: 7, color: Color::Black})
+                    Some(Piece { piece_type: PieceType::Rook, row: 0, col: 0, color: Color::Black}), 
+                    Some(Piece { piece_type: PieceType::Knight, row: 0, col: 1, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Bishop, row: 0, col: 2, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Queen, row: 0, col: 3, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::King, row: 0, col: 4, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Bishop, row: 0, col: 5, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Knight, row: 0, col: 6, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Rook, row: 0, col: 7, color: Color::Black})
                 ],
                 [
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 0, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 1, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 2, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 3, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 4, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 5, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 6, color: Color::Black}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 1, col: 7, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 0, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 1, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 2, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 3, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 4, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 5, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 6, color: Color::Black}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 1, col: 7, color: Color::Black}),
                 ],
                 [
                     None, None, None, None, None, None, None, None
@@ -60,32 +87,55 @@ impl GameState {
                     None, None, None, None, None, None, None, None
                 ],
                 [
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 0, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 1, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 2, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 3, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 4, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 5, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 6, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Pawn, has_moved: false, row: 6, col: 7, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 0, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 1, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 2, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 3, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 4, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 5, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 6, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Pawn, row: 6, col: 7, color: Color::White}),
                 ],
                 [
-                    Some(Piece { piece_type: PieceType::Rook, has_moved: false, row: 7, col: 0, color: Color::White}), 
-                    Some(Piece { piece_type: PieceType::Knight, has_moved: false, row: 7, col: 1, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Bishop, has_moved: false, row: 7, col: 2, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Queen, has_moved: false, row: 7, col: 3, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::King, has_moved: false, row: 7, col: 4, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Bishop, has_moved: false, row: 7, col: 5, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Knight, has_moved: false, row: 7, col: 6, color: Color::White}),
-                    Some(Piece { piece_type: PieceType::Rook, has_moved: false, row: 7, col: 7, color: Color::White})
+                    Some(Piece { piece_type: PieceType::Rook, row: 7, col: 0, color: Color::White}), 
+                    Some(Piece { piece_type: PieceType::Knight, row: 7, col: 1, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Bishop, row: 7, col: 2, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Queen, row: 7, col: 3, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::King, row: 7, col: 4, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Bishop, row: 7, col: 5, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Knight, row: 7, col: 6, color: Color::White}),
+                    Some(Piece { piece_type: PieceType::Rook, row: 7, col: 7, color: Color::White})
                 ],
             ],
+            bitboards: Bitboards::default(),
             rows: 8,
             cols: 8,
-            last_move: (None, None),
+            last_move: None,
+            moves: Vec::new(),
+            initial_fen: START_FEN.to_string(),
             turn: Color::White,
-            checking_state: false
-        }
+            castling: CastlingRights::all(),
+            en_passant: None,
+            status: GameStatus::Ongoing,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+            position_history: Vec::new()
+        };
+
+        game_state.bitboards = Bitboards::from_grid(&game_state.grid);
+        game_state.hash = position_hash(&game_state);
+        game_state.position_history.push(game_state.hash);
+        game_state
+    }
+
+    /// Sets up the position described by a FEN string.
+    pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+        from_fen(fen)
+    }
+
+    pub fn to_fen(&self) -> String {
+        to_fen(self)
     }
 
     #[allow(dead_code)]
@@ -98,119 +148,170 @@ impl GameState {
             }
         }
 
-        return pl;
+        pl
     }
 
-    pub fn move_piece(&mut self, from_row: usize, from_col: usize, to_row: usize, to_col: usize) {
-        let piece = self.grid[from_row][from_col];
+    pub fn move_piece(&mut self, from_row: usize, from_col: usize, to_row: usize, to_col: usize, promotion: Option<PieceType>) -> Result<Move, MoveError> {
+        let from = Coord { row: from_row, col: from_col };
+        let to = Coord { row: to_row, col: to_col };
+
+        let piece = if self.in_bounds(from_row, from_col) { self.grid[from_row][from_col] } else { None };
         match piece {
-            Some(mut piece) => {
-    
-                let moves = self.possible_moves(piece);
-                let move_coord = Coord { row:to_row, col:to_col };
-
-                // if the chosen move is in the generated possible moves
-                if moves.contains(&move_coord) {
-                    piece.has_moved = true;
-                    piece.row = to_row;
-                    piece.col = to_col;
-
-                    if piece.color == Color::White && from_row == 3 
-                    || piece.color == Color::Black && from_row == 4 {
-                        match piece.piece_type {
-                            PieceType::Pawn => {
-                                let last_piece = self.last_move.0;
-                                last_piece.map(|last_piece|
-                                    if last_piece.piece_type == PieceType::Pawn && last_piece.col != from_col {
-                                        self.grid[last_piece.row][last_piece.col] = None;
-                                    }
-                                );
-                            },
-                            _ => ()
-                        }
-                    } else if piece.piece_type == PieceType::Pawn 
-                    && to_row == (if piece.color == Color::White {0} else {self.rows - 1}) {
-                        // TODO: handle promotion properly
-                        // for the moment, just promote to queen
-                        piece.piece_type = PieceType::Queen;
-                    }
+            Some(piece) => {
+                if self.status.is_over() {
+                    return Err(MoveError::GameOver);
+                }
 
-                    if piece.piece_type == PieceType::King{
-                        if (from_col as isize - to_col as isize).abs() == 2{
-                            if to_col == 2{
-                                let mut rook = self.grid[from_row][0];
-                                rook.map(|mut rook|{
-                                    rook.col = 3;
-                                    self.grid[to_row][3] = Some(rook);
-                                    self.grid[to_row][0] = None;
-                                });
-                            }
-
-                            else if to_col ==  6{
-                                let mut rook = self.grid[from_row][7];
-                                rook.map(|mut rook|{
-                                    rook.col = 5;
-                                    self.grid[to_row][5] = Some(rook);
-                                    self.grid[to_row][7] = None;
-                                });
-                            }
-                        }
-                    }
-                    
-                    self.last_move = (Some(piece), Some(Coord { row: from_row, col: from_col }));
-                    self.grid[to_row][to_col] = Some(piece); 
-                    self.grid[from_row][from_col] = None;
-                    match self.turn {
-                        Color::White => self.turn = Color::Black,
-                        _ => self.turn = Color::White
-                    };
-                } else {
-                    if !self.checking_state {
-                        println!("[Error] Attempt to move piece to invalid square")
-                    }
+                if piece.color != self.turn {
+                    return Err(MoveError::WrongTurn(self.turn));
+                }
+
+                let candidates: Vec<Move> = self.possible_moves(piece).into_iter()
+                    .filter(|candidate| candidate.to == to)
+                    .collect();
+
+                if candidates.is_empty() {
+                    return Err(MoveError::IllegalDestination { from, to });
+                }
+
+                // a pawn reaching the back rank has one candidate per promotion piece
+                let is_promotion = candidates.iter().any(|candidate| candidate.promotion().is_some());
+                match promotion {
+                    None if is_promotion => return Err(MoveError::PromotionRequired),
+                    Some(piece_type) if !is_promotion => return Err(MoveError::InvalidPromotion(piece_type)),
+                    _ => ()
+                }
+
+                match candidates.into_iter().find(|candidate| candidate.promotion() == promotion) {
+                    Some(chosen) => {
+                        self.make_move(chosen);
+                        self.moves.push(chosen);
+
+                        self.position_history.push(self.hash);
+
+                        self.status = self.evaluate_status();
+                        Ok(chosen)
+                    },
+                    None => Err(MoveError::InvalidPromotion(promotion.unwrap_or(PieceType::Pawn)))
                 }
             },
-            None => println!("[Error] Attempt to move an invalid piece")
+            None => Err(MoveError::NoPiece(from))
         }
     }
 
-    pub fn in_bounds(&self, row: usize, col: usize) -> bool {
-        row < self.rows && col < self.cols
-    }
+    /// Plays a move produced by one of the generators without checking it,
+    /// leaving the move list, history and status alone. Hand the returned
+    /// `Undo` to `unmake_move` to take the move back.
+    pub fn make_move(&mut self, chosen: Move) -> Undo {
+        let undo = Undo {
+            chosen,
+            rook: chosen.rook_hop().and_then(|(rook_from, _)| self.grid[rook_from.row][rook_from.col]),
+            last_move: self.last_move,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash
+        };
+        self.hash ^= state_hash(self) ^ move_hash(&chosen);
+        self.bitboards.apply(&chosen);
+
+        let mut piece = chosen.piece;
+        piece.row = chosen.to.row;
+        piece.col = chosen.to.col;
 
-    pub fn valid_move(&self, piece: Piece, row: usize, col: usize) -> bool {
-        if !self.in_bounds(row, col) {
-            return false;
-        } else if piece.piece_type == PieceType::King && in_check(*self, Coord {row, col}, piece) {
-            return false;
+        match chosen.kind {
+            // the captured pawn stands beside the capturing one, not on the square it moves to
+            MoveKind::EnPassant => self.grid[chosen.from.row][chosen.to.col] = None,
+            MoveKind::Promotion(piece_type) => piece.piece_type = piece_type,
+            _ => ()
         }
 
-        if !self.checking_state { 
-            let mut game_state_copy = *self;
-            game_state_copy.checking_state = true;
-            game_state_copy.move_piece(piece.row, piece.col, row, col);
-            for other_piece in game_state_copy.piece_list() {
-                if other_piece.color == piece.color && other_piece.piece_type == PieceType::King {
-                    if in_check(game_state_copy, Coord { row: other_piece.row, col: other_piece.col }, other_piece) {
-                        return false;
-                    }
-                }
+        if let Some((rook_from, rook_to)) = chosen.rook_hop() {
+            if let Some(mut rook) = self.grid[rook_from.row][rook_from.col] {
+                rook.row = rook_to.row;
+                rook.col = rook_to.col;
+                self.grid[rook_to.row][rook_to.col] = Some(rook);
+                self.grid[rook_from.row][rook_from.col] = None;
             }
         }
 
-        let target_piece = self.grid[row][col];
-        match target_piece {
-            Some(target_piece) => !(piece.color == target_piece.color || piece.row == row && piece.col == col),
+        self.castling.touch(chosen.from);
+        self.castling.touch(chosen.to);
+        self.en_passant = match chosen.kind {
+            MoveKind::DoublePush => Some(Coord { row: (chosen.from.row + chosen.to.row) / 2, col: chosen.to.col }),
+            _ => None
+        };
+
+        if chosen.piece.piece_type == PieceType::Pawn || chosen.is_capture() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if chosen.piece.color == Color::Black {
+            self.fullmove_number += 1;
+        }
+
+        self.last_move = Some(chosen);
+        self.grid[chosen.to.row][chosen.to.col] = Some(piece);
+        self.grid[chosen.from.row][chosen.from.col] = None;
+        self.turn = self.turn.opponent();
+        self.hash ^= state_hash(self);
+
+        undo
+    }
+
+    /// Takes back the move `undo` came from, which must be the last one
+    /// made, leaving the position exactly as it was before.
+    pub fn unmake_move(&mut self, undo: Undo) {
+        let chosen = undo.chosen;
+        // the bitboard updates are their own inverse
+        self.bitboards.apply(&chosen);
+
+        self.grid[chosen.to.row][chosen.to.col] = None;
+        self.grid[chosen.from.row][chosen.from.col] = Some(chosen.piece);
+        if let Some(captured) = chosen.captured {
+            self.grid[captured.row][captured.col] = Some(captured);
+        }
+        if let Some((rook_from, rook_to)) = chosen.rook_hop() {
+            self.grid[rook_to.row][rook_to.col] = None;
+            self.grid[rook_from.row][rook_from.col] = undo.rook;
+        }
+
+        if chosen.piece.color == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        self.turn = chosen.piece.color;
+        self.last_move = undo.last_move;
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+    }
+
+    pub fn in_bounds(&self, row: usize, col: usize) -> bool {
+        row < self.rows && col < self.cols
+    }
+
+    /// Whether the mover's king is out of check once `chosen` is played.
+    /// Only the bitboards are played out, which is all the attack test needs.
+    pub fn leaves_king_safe(&self, chosen: Move) -> bool {
+        let color = chosen.piece.color;
+        let mut after = self.bitboards;
+        after.apply(&chosen);
+
+        match after.king_square(color) {
+            Some(index) => !attacked_on(&after, index, color.opponent()),
             None => true
         }
     }
 
-    pub fn print_moves(&self, moves: HashSet<Coord>) {
+    pub fn print_moves(&self, moves: HashSet<Move>) {
+        let moves: HashSet<Coord> = moves.iter().map(|possible| possible.to).collect();
         let mut output = String::new();
 
         output.push_str("  ");
         for col in 0..self.cols { output.push_str(&format!("{} ", col)); }
-        output.push_str("\n");
+        output.push('\n');
         for row in 0..self.rows {
             output.push_str(&format!("{} ", row));
             for col in 0..self.cols {
@@ -227,7 +328,7 @@ impl GameState {
             output.push('\n');
         }
         for coord in moves.iter() {
-            output.push_str(&format!("({}, {}), ", coord.row, coord.col));
+            output.push_str(&format!("{} ({}, {}), ", coord, coord.row, coord.col));
         }
         output.pop();
         output.pop();
@@ -235,35 +336,209 @@ impl GameState {
         println!("{}", output);
     }
    
-    pub fn possible_moves(&self, piece: Piece) -> HashSet<Coord> {
-        let moves = match piece.piece_type {
-            PieceType::Knight =>    possible_knight_moves(&self, piece),
-            PieceType::Bishop =>    possible_bishop_moves(&self, piece),
-            PieceType::Queen =>     possible_queen_moves(&self, piece),
-            PieceType::Pawn =>      possible_pawn_moves(&self, piece),
-            PieceType::Rook =>      possible_rook_moves(&self, piece),
-            PieceType::King =>      possible_king_moves(&self, piece),
+    pub fn possible_moves(&self, piece: Piece) -> HashSet<Move> {
+        match piece.piece_type {
+            PieceType::Knight =>    possible_knight_moves(self, piece),
+            PieceType::Bishop =>    possible_bishop_moves(self, piece),
+            PieceType::Queen =>     possible_queen_moves(self, piece),
+            PieceType::Pawn =>      possible_pawn_moves(self, piece),
+            PieceType::Rook =>      possible_rook_moves(self, piece),
+            PieceType::King =>      possible_king_moves(self, piece),
+        }
+    }
+
+    pub fn insert_if_valid(&self, piece: Piece, row: usize, col: usize, moves: &mut HashSet<Move>) {
+        self.insert_move_if_valid(piece, row, col, MoveKind::Quiet, moves);
+    }
+
+    pub fn insert_move_if_valid(&self, piece: Piece, row: usize, col: usize, kind: MoveKind, moves: &mut HashSet<Move>) {
+        if !self.in_bounds(row, col) || self.bitboards.color(piece.color) & square_bit(row, col) != EMPTY {
+            return;
+        }
+
+        let chosen = self.build_move(piece, Coord { row, col }, kind);
+        if self.leaves_king_safe(chosen) {
+            moves.insert(chosen);
+        }
+    }
+
+    /// Adds a plain move to each of `targets` that does not hold a piece of
+    /// the mover's own color and does not leave its king in check.
+    pub fn insert_targets(&self, piece: Piece, targets: Bitboard, moves: &mut HashSet<Move>) {
+        for index in squares(targets & !self.bitboards.color(piece.color)) {
+            let chosen = self.build_move(piece, index_coord(index), MoveKind::Quiet);
+            if self.leaves_king_safe(chosen) {
+                moves.insert(chosen);
+            }
+        }
+    }
+
+    pub fn build_move(&self, piece: Piece, to: Coord, kind: MoveKind) -> Move {
+        let captured = match kind {
+            MoveKind::EnPassant => self.grid[piece.row][to.col],
+            _ => self.grid[to.row][to.col]
         };
 
-        moves
+        Move {
+            from: Coord { row: piece.row, col: piece.col },
+            to,
+            piece,
+            captured,
+            kind
+        }
     }
 
-    pub fn insert_if_valid(&self, piece: Piece, row: usize, col: usize, moves: &mut HashSet<Coord>) {
-        if self.valid_move(piece, row, col) {
-            moves.insert(Coord { row, col });
+    pub fn is_square_attacked(&self, coord: Coord, by_color: Color) -> bool {
+        is_square_attacked(self, coord, by_color)
+    }
+
+    pub fn attacked_squares(&self, by_color: Color) -> Vec<Coord> {
+        attacked_squares(self, by_color)
+    }
+
+    pub fn is_in_check(&self, color: Color) -> bool {
+        match self.bitboards.king_square(color) {
+            Some(index) => attacked_on(&self.bitboards, index, color.opponent()),
+            None => false
+        }
+    }
+
+    /// Works out the status of the position from scratch. `move_piece` keeps
+    /// `status` up to date with this after every move.
+    pub fn evaluate_status(&self) -> GameStatus {
+        let in_check = self.is_in_check(self.turn);
+
+        if self.no_available_moves(self.turn) {
+            if in_check {
+                GameStatus::Checkmate { winner: self.turn.opponent() }
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if let Some(reason) = automatic_draw(self) {
+            GameStatus::Draw { reason }
+        } else if in_check {
+            GameStatus::Check
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// The draw the side to move could claim now, if any.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.status.is_over() {
+            return None;
+        }
+
+        claimable_draw(self)
+    }
+
+    /// Ends the game as a draw if the rules allow a claim right now.
+    pub fn claim_draw(&mut self) -> Option<DrawReason> {
+        let reason = self.claimable_draw();
+
+        if let Some(reason) = reason {
+            self.status = GameStatus::Draw { reason };
         }
+        reason
+    }
+
+    /// Every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.piece_list().into_iter()
+            .filter(|piece| piece.color == self.turn)
+            .flat_map(|piece| self.possible_moves(piece))
+            .collect()
+    }
+
+    pub fn perft(&self, depth: usize) -> u64 {
+        perft(self, depth)
+    }
+
+    pub fn divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        divide(self, depth)
     }
 
     pub fn no_available_moves(&self, color: Color) -> bool {
         for piece in self.piece_list().iter() {
-            if piece.color == color {
-                if !self.possible_moves(*piece).is_empty() {
-                    return false;
-                }
+            if piece.color == color && !self.possible_moves(*piece).is_empty() {
+                return false;
             }
         }
 
-        return true;
+        true
+    }
+}
+
+/// What `make_move` overwrote that the move itself does not record.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Undo {
+    pub chosen: Move,
+    // the castling rook as it stood before the castle
+    rook: Option<Piece>,
+    last_move: Option<Move>,
+    castling: CastlingRights,
+    en_passant: Option<Coord>,
+    halfmove_clock: u32,
+    hash: u64
+}
+
+/// Where the game stands, from the point of view of the side to move.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum GameStatus {
+    Ongoing,
+    /// The side to move is in check but has a way out.
+    Check,
+    Checkmate { winner: Color },
+    /// The side to move has no legal moves but is not in check.
+    Stalemate,
+    Draw { reason: DrawReason }
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        !matches!(*self, GameStatus::Ongoing | GameStatus::Check)
+    }
+}
+
+/// Why `move_piece` refused a move. The board is left untouched.
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub enum MoveError {
+    /// There is no piece on the starting square.
+    NoPiece(Coord),
+    /// The piece belongs to the side not on move; holds the side that is.
+    WrongTurn(Color),
+    /// The piece has no legal move to that square.
+    IllegalDestination { from: Coord, to: Coord },
+    /// The game has already ended.
+    GameOver,
+    /// A pawn reaching the back rank needs to be told what to promote to.
+    PromotionRequired,
+    /// The promotion piece is not a queen, rook, bishop or knight, or the
+    /// move is not a promotion at all.
+    InvalidPromotion(PieceType)
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MoveError::NoPiece(at) => write!(f, "No piece on {}", at),
+            MoveError::WrongTurn(turn) => write!(f, "It is {:?}'s turn", turn),
+            MoveError::IllegalDestination { from, to } => {
+                write!(f, "Piece on {} cannot move to {}", from, to)
+            },
+            MoveError::GameOver => write!(f, "The game is over"),
+            MoveError::PromotionRequired => write!(f, "Choose a piece to promote to"),
+            MoveError::InvalidPromotion(piece_type) => write!(f, "Cannot promote to {:?} here", piece_type)
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+impl Default for GameState {
+    fn default() -> GameState {
+        GameState::new()
     }
 }
 
@@ -272,7 +547,7 @@ impl fmt::Display for GameState {
         let mut board_rep = String::new();
         board_rep.push_str("  ");
         for col in 0..self.cols { board_rep.push_str(&format!("{} ", col)); }
-        board_rep.push_str("\n");
+        board_rep.push('\n');
         for row in 0..self.rows {
             board_rep.push_str(&format!("{} ", row));
             for col in  0..self.cols {
@@ -287,4 +562,79 @@ impl fmt::Display for GameState {
         board_rep.pop();
         write!(f, "{}", board_rep)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::perft::test_support::play;
+    use game::piece::UciMove;
+
+    // `move_piece` on a move in UCI notation, checking a refused move leaves the board as it was
+    fn attempt(game_state: &mut GameState, uci: &str) -> Result<Move, MoveError> {
+        let uci = UciMove::parse(uci).unwrap();
+        let before = game_state.to_fen();
+        let result = game_state.move_piece(uci.from.row, uci.from.col, uci.to.row, uci.to.col, uci.promotion);
+        if result.is_err() {
+            assert_eq!(game_state.to_fen(), before);
+        }
+        result
+    }
+
+    fn square(name: &str) -> Coord {
+        Coord::from_square(name).unwrap()
+    }
+
+    #[test]
+    fn fools_mate_is_checkmate_for_black() {
+        let mut game_state = GameState::new();
+        play(&mut game_state, "f2f3 e7e5 g2g4");
+        assert_eq!(game_state.status, GameStatus::Ongoing);
+
+        play(&mut game_state, "d8h4");
+        assert_eq!(game_state.status, GameStatus::Checkmate { winner: Color::Black });
+        assert!(game_state.status.is_over());
+    }
+
+    #[test]
+    fn no_moves_and_no_check_is_stalemate() {
+        let mut game_state = GameState::from_fen("k7/8/8/1Q6/8/8/8/7K w - - 0 1").unwrap();
+        play(&mut game_state, "b5b6");
+        assert_eq!(game_state.status, GameStatus::Stalemate);
+        assert!(game_state.status.is_over());
+
+        assert_eq!(GameState::from_fen("k7/8/1Q6/8/8/8/8/7K b - - 0 1").unwrap().status, GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn each_refused_move_has_its_own_error() {
+        let mut game_state = GameState::new();
+        assert_eq!(attempt(&mut game_state, "e4e5").err(), Some(MoveError::NoPiece(square("e4"))));
+        assert_eq!(attempt(&mut game_state, "e7e5").err(), Some(MoveError::WrongTurn(Color::White)));
+        assert_eq!(attempt(&mut game_state, "e2e5").err(), Some(MoveError::IllegalDestination { from: square("e2"), to: square("e5") }));
+
+        // a pinned piece may not step out of the line, nor may a move ignore a check
+        let mut pinned = GameState::from_fen("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap();
+        assert_eq!(attempt(&mut pinned, "e2d3").err(), Some(MoveError::IllegalDestination { from: square("e2"), to: square("d3") }));
+        let mut checked = GameState::from_fen("4k3/4r3/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(attempt(&mut checked, "a1a2").err(), Some(MoveError::IllegalDestination { from: square("a1"), to: square("a2") }));
+        assert!(attempt(&mut checked, "e1d1").is_ok());
+
+        let mut mated = GameState::new();
+        play(&mut mated, "f2f3 e7e5 g2g4 d8h4");
+        assert_eq!(attempt(&mut mated, "e2e4").err(), Some(MoveError::GameOver));
+    }
+
+    #[test]
+    fn a_promotion_needs_a_piece_and_only_a_promotion_takes_one() {
+        let mut game_state = GameState::from_fen("8/P6k/8/8/8/8/4P3/K7 w - - 0 1").unwrap();
+        assert_eq!(attempt(&mut game_state, "a7a8").err(), Some(MoveError::PromotionRequired));
+        for &piece_type in [PieceType::King, PieceType::Pawn].iter() {
+            assert_eq!(game_state.move_piece(1, 0, 0, 0, Some(piece_type)).err(), Some(MoveError::InvalidPromotion(piece_type)));
+        }
+        assert_eq!(attempt(&mut game_state, "e2e4q").err(), Some(MoveError::InvalidPromotion(PieceType::Queen)));
+        assert_eq!(game_state.to_fen(), "8/P6k/8/8/8/8/4P3/K7 w - - 0 1");
+
+        assert_eq!(attempt(&mut game_state, "a7a8n").unwrap().kind, MoveKind::Promotion(PieceType::Knight));
+    }
+}
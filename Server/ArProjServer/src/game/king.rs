@@ -1,105 +1,48 @@
 use std::collections::HashSet;
 
+use game::attack::{is_square_attacked, king_attacks};
+use game::bitboard::square_index;
 use game::gamestate::GameState;
 use game::piece::Piece;
-use game::piece::PieceType;
 use game::piece::Coord;
+use game::piece::Move;
+use game::piece::MoveKind;
+use game::piece::PieceType;
 
-pub fn possible_king_moves(game_state: &GameState, piece: Piece) -> HashSet<Coord> {
+pub fn possible_king_moves(game_state: &GameState, piece: Piece) -> HashSet<Move> {
     let mut moves = HashSet::new();
 
-    if !piece.has_moved{
-        let rook1 = game_state.grid[piece.row][0];
-        let rook2 = game_state.grid[piece.row][7];
-
-        rook1.map(|rook1|{
-            if !rook1.has_moved{
-                if game_state.grid[piece.row][1].is_none() && game_state.grid[piece.row][2].is_none() && game_state.grid[piece.row][3].is_none(){
-                    game_state.insert_if_valid(piece, piece.row, 2, &mut moves);
-                    }
-            }
-        });
-
-        rook2.map(|rook2| {
-            if !rook2.has_moved{
-                if game_state.grid[piece.row][5].is_none() && game_state.grid[piece.row][6].is_none(){
-                    game_state.insert_if_valid(piece, piece.row, 6, &mut moves);
-                }
-            }
-        });
-    }
-    if piece.row > 0 {
-        // up
-        let row = piece.row - 1;
-        let col = piece.col;
-        game_state.insert_if_valid(piece, row, col, &mut moves);
-    }
-
-    // down
-    let mut row = piece.row + 1;
-    let mut col = piece.col;
-    game_state.insert_if_valid(piece, row, col, &mut moves);
-
-    if piece.col > 0 {
-        // left
-        row = piece.row;
-        col = piece.col - 1;
-        game_state.insert_if_valid(piece, row, col, &mut moves);
+    for &kind in [MoveKind::CastleKingside, MoveKind::CastleQueenside].iter() {
+        if can_castle(game_state, piece, kind) {
+            let col = if kind == MoveKind::CastleKingside { 6 } else { 2 };
+            game_state.insert_move_if_valid(piece, piece.row, col, kind, &mut moves);
+        }
     }
 
-    // right
-    row = piece.row;
-    col = piece.col + 1;
-    game_state.insert_if_valid(piece, row, col, &mut moves);
-
-    //down positive diagonal
-    row = piece.row + 1;
-    col = piece.col + 1;
-    game_state.insert_if_valid(piece, row, col, &mut moves);
+    game_state.insert_targets(piece, king_attacks(square_index(piece.row, piece.col)), &mut moves);
 
-    if piece.col > 0 {
-        //up positive diagonal
-        row = piece.row + 1;
-        col = piece.col - 1;
-        game_state.insert_if_valid(piece, row, col, &mut moves);
-    }
+    moves
+}
 
-    if piece.row > 0 && piece.col > 0 { 
-        //up negative diagonal
-        row = piece.row - 1;
-        col = piece.col - 1;
-        game_state.insert_if_valid(piece, row, col, &mut moves);
+// everything but the king's destination, which is checked like any other move: the right
+// is still held, the rook is home, the squares between are empty and the king neither
+// starts on nor passes over an attacked square
+fn can_castle(game_state: &GameState, king: Piece, kind: MoveKind) -> bool {
+    if !game_state.castling.allows(king.color, kind) {
+        return false;
     }
 
-    if piece.row > 0 { 
-        //down negative diagonal
-        row = piece.row - 1;
-        col = piece.col + 1;
-        game_state.insert_if_valid(piece, row, col, &mut moves);
+    let (rook_col, between, passed_col) = match kind {
+        MoveKind::CastleKingside => (7, 5..7, 5),
+        _ => (0, 1..4, 3)
+    };
+    match game_state.grid[king.row][rook_col] {
+        Some(rook) if rook.piece_type == PieceType::Rook && rook.color == king.color => (),
+        _ => return false
     }
 
-    return moves;
+    let enemy = king.color.opponent();
+    between.into_iter().all(|col| game_state.grid[king.row][col].is_none())
+        && !is_square_attacked(game_state, Coord { row: king.row, col: king.col }, enemy)
+        && !is_square_attacked(game_state, Coord { row: king.row, col: passed_col }, enemy)
 }
-
-pub fn in_check(game_state: GameState, pos: Coord, piece: Piece) -> bool {
-    for row in 0..game_state.rows {
-        for col in 0..game_state.cols {
-            let curr_piece = game_state.grid[row][col];
-            let check = match curr_piece {
-                Some(curr_piece) => {
-                    if curr_piece.color != piece.color && curr_piece.piece_type != PieceType::King {
-                        game_state.possible_moves(curr_piece).contains(&pos)
-                    } else {
-                        false
-                    }
-                },
-                None => false
-            };
-
-            if check {
-                return true;
-            }
-        }
-    }
-    return false;
-}
\ No newline at end of file
@@ -1,8 +1,17 @@
 pub mod gamestate;
+pub mod attack;
+pub mod bitboard;
+pub mod castling;
+pub mod draw;
+pub mod fen;
+pub mod perft;
+pub mod pgn;
+pub mod san;
 pub mod piece;
 pub mod king;
 pub mod pawn;
 pub mod rook;
 pub mod bishop;
 pub mod knight;
-pub mod queen;
\ No newline at end of file
+pub mod queen;
+pub mod zobrist;
@@ -1,77 +1,60 @@
 use std::collections::HashSet;
 
+use game::attack::pawn_attacks;
+use game::bitboard::{index_coord, square_bit, square_index, squares, EMPTY};
 use game::gamestate::GameState;
 use game::piece::Piece;
 use game::piece::Color;
-use game::piece::Coord;
+use game::piece::Move;
+use game::piece::MoveKind;
+use game::piece::PieceType;
 
-pub fn possible_pawn_moves(game_state: &GameState, piece: Piece) -> HashSet<Coord> {
-    let mut moves = HashSet::new();
-
-    let mut row = piece.row;
-    let mut col = piece.col;
+pub const PROMOTION_PIECES: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
 
-    if piece.color == Color::White {
-        row -= 1;
-    } else {
-        row += 1;
-    }
-
-    if game_state.in_bounds(row, col) && game_state.grid[row][col].is_none() {
-        game_state.insert_if_valid(piece, row, col, &mut moves);
-    }
-    
-    if !piece.has_moved {
-        if piece.color == Color::White {
-            row -= 1;
+pub fn possible_pawn_moves(game_state: &GameState, piece: Piece) -> HashSet<Move> {
+    let mut moves = HashSet::new();
+    let (start_row, en_passant_row, last_row) = match piece.color {
+        Color::White => (6, 3, 0),
+        Color::Black => (1, 4, game_state.rows - 1)
+    };
+    let insert_advance = |index: usize, moves: &mut HashSet<Move>| {
+        let to = index_coord(index);
+        if to.row == last_row {
+            for promotion in PROMOTION_PIECES.iter() {
+                game_state.insert_move_if_valid(piece, to.row, to.col, MoveKind::Promotion(*promotion), moves);
+            }
         } else {
-            row += 1;
+            game_state.insert_if_valid(piece, to.row, to.col, moves);
         }
-        
-        if game_state.in_bounds(row, col) && game_state.grid[row][col].is_none() {
-            game_state.insert_if_valid(piece, row, col, &mut moves);
-        }
-    }
+    };
 
-    row = piece.row;
-    col = piece.col + 1;
+    // pawns never stand on the back ranks, so a step forward stays on the board
+    let from = square_index(piece.row, piece.col);
+    let step = |index: usize| if piece.color == Color::White { index - 8 } else { index + 8 };
+    let occupied = game_state.bitboards.occupied();
 
-    if piece.color == Color::White {
-        row -= 1;
-    } else {
-        row += 1;
-    }
-
-    if game_state.in_bounds(row, col) && !game_state.grid[row][col].is_none() {
-        game_state.insert_if_valid(piece, row, col, &mut moves);
-    } else if piece.color == Color::White && piece.row == 3 || piece.color == Color::Black && piece.row == 4 {
-        let last_piece = game_state.last_move.0;
+    // the double push needs the square in between to be empty as well
+    let single = step(from);
+    if occupied & 1 << single == EMPTY {
+        insert_advance(single, &mut moves);
 
-        last_piece.map(|last_piece| {
-            if last_piece.row == piece.row && last_piece.col == col {
-                game_state.insert_if_valid(piece, row, col, &mut moves);
-            }
-        });
+        if piece.row == start_row && occupied & 1 << step(single) == EMPTY {
+            let to = index_coord(step(single));
+            game_state.insert_move_if_valid(piece, to.row, to.col, MoveKind::DoublePush, &mut moves);
+        }
     }
 
-    col = piece.col;
-
-    if col > 0 {
-        col = piece.col - 1;
+    let attacks = pawn_attacks(piece.color, from);
+    for index in squares(attacks & game_state.bitboards.color(piece.color.opponent())) {
+        insert_advance(index, &mut moves);
     }
-    
-    if game_state.in_bounds(row, col) && !game_state.grid[row][col].is_none() {
-        game_state.insert_if_valid(piece, row, col, &mut moves);
-    } else if piece.color == Color::White && piece.row == 3 || piece.color == Color::Black && piece.row == 4 {
-        let last_piece = game_state.last_move.0;
 
-        last_piece.map(|last_piece| {
-            if last_piece.row == piece.row && last_piece.col == col {
-                game_state.insert_if_valid(piece, row, col, &mut moves);
-            }
-        });
+    // the en passant square is behind the opponent's pawn, so only a pawn beside that pawn may take
+    if let Some(target) = game_state.en_passant {
+        if piece.row == en_passant_row && attacks & square_bit(target.row, target.col) != EMPTY {
+            game_state.insert_move_if_valid(piece, target.row, target.col, MoveKind::EnPassant, &mut moves);
+        }
     }
 
-    return moves
+    moves
 }
-
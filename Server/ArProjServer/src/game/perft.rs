@@ -0,0 +1,208 @@
+use game::gamestate::GameState;
+use game::piece::Move;
+
+/// Counts the leaf positions of the legal move tree `depth` plies deep.
+/// Comparing the count against known values is the standard check that a
+/// move generator follows the rules.
+pub fn perft(game_state: &GameState, depth: usize) -> u64 {
+    count_leaves(&mut game_state.clone(), depth)
+}
+
+/// `perft` split by first move, sorted by the move's UCI string, which is
+/// how other engines print it and makes a wrong count easy to track down.
+pub fn divide(game_state: &GameState, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut position = game_state.clone();
+    let mut counts: Vec<(Move, u64)> = game_state.legal_moves().into_iter().map(|chosen| {
+        let undo = position.make_move(chosen);
+        let nodes = count_leaves(&mut position, depth - 1);
+        position.unmake_move(undo);
+        (chosen, nodes)
+    }).collect();
+
+    counts.sort_by_key(|&(chosen, _)| chosen.uci().to_string());
+    counts
+}
+
+// walks the tree on one position, making and unmaking each move
+fn count_leaves(position: &mut GameState, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves.into_iter().map(|chosen| {
+        let undo = position.make_move(chosen);
+        let nodes = count_leaves(position, depth - 1);
+        position.unmake_move(undo);
+        nodes
+    }).sum()
+}
+
+/// Positions and helpers shared by the rules engine's tests.
+#[cfg(test)]
+pub mod test_support {
+    use game::bitboard::Bitboards;
+    use game::gamestate::GameState;
+    use game::piece::UciMove;
+    use game::zobrist::position_hash;
+
+    // node counts from the Chess Programming Wiki's perft results page
+    pub const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    pub const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    pub const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    pub const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+    pub const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    pub const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    /// Castling both ways, promotions with and without captures, and an en
+    /// passant capture ready to be taken: between them they reach every
+    /// kind of move.
+    pub const EVERY_KIND_OF_MOVE: [&str; 3] = [KIWIPETE, POSITION_4, "4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1"];
+
+    /// Plays space separated UCI moves, which must all be legal.
+    pub fn play(game_state: &mut GameState, moves: &str) {
+        for uci in moves.split_whitespace() {
+            let uci = UciMove::parse(uci).unwrap();
+            game_state.move_piece(uci.from.row, uci.from.col, uci.to.row, uci.to.col, uci.promotion).unwrap();
+        }
+    }
+
+    /// Makes and unmakes every move `depth` plies below the position,
+    /// checking along the way that the bitboards follow the grid, that the
+    /// incremental hash matches one worked out from scratch, and that
+    /// unmaking a move leaves nothing behind.
+    pub fn assert_consistent(game_state: &mut GameState, depth: usize) {
+        let fen = game_state.to_fen();
+        assert_eq!(game_state.bitboards, Bitboards::from_grid(&game_state.grid), "bitboards of {}", fen);
+        assert_eq!(game_state.hash, position_hash(game_state), "hash of {}", fen);
+        if depth == 0 {
+            return;
+        }
+
+        let grid = game_state.grid;
+        let bitboards = game_state.bitboards;
+        let last_move = game_state.last_move;
+        let hash = game_state.hash;
+
+        for chosen in game_state.legal_moves() {
+            let undo = game_state.make_move(chosen);
+            assert_consistent(game_state, depth - 1);
+            game_state.unmake_move(undo);
+
+            assert!(game_state.grid == grid, "grid after {} from {}", chosen.uci(), fen);
+            assert_eq!(game_state.bitboards, bitboards);
+            assert_eq!(game_state.last_move, last_move);
+            assert_eq!(game_state.hash, hash);
+            assert_eq!(game_state.to_fen(), fen);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_support::*;
+    use game::fen::START_FEN;
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let game_state = GameState::from_fen(fen).unwrap();
+        for (depth, &nodes) in expected.iter().enumerate() {
+            assert_eq!(perft(&game_state, depth + 1), nodes, "perft({}) of {}", depth + 1, fen);
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        assert_perft(START_FEN, &[20, 400, 8_902, 197_281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2_039, 97_862]);
+    }
+
+    #[test]
+    fn position_3() {
+        assert_perft(POSITION_3, &[14, 191, 2_812, 43_238]);
+    }
+
+    #[test]
+    fn position_4() {
+        assert_perft(POSITION_4, &[6, 264, 9_467]);
+    }
+
+    #[test]
+    fn position_4_mirrored() {
+        assert_perft(POSITION_4_MIRRORED, &[6, 264, 9_467]);
+    }
+
+    #[test]
+    fn position_5() {
+        assert_perft(POSITION_5, &[44, 1_486, 62_379]);
+    }
+
+    #[test]
+    fn position_6() {
+        assert_perft(POSITION_6, &[46, 2_079, 89_890]);
+    }
+
+    #[test]
+    fn kings_keep_their_distance() {
+        // only the two squares away from the black king are safe for the white one
+        assert_perft("8/8/8/3k4/8/3K4/8/8 w - - 0 1", &[5]);
+        assert_perft("8/8/8/8/8/1k6/8/K7 w - - 0 1", &[1]);
+    }
+
+    #[test]
+    fn castling_avoids_attacked_squares() {
+        // b1 is only crossed by the rook, so an attack on it does not stop O-O-O
+        assert_perft("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[26, 357]);
+        // through d1 or f1, into g1, and out of check
+        assert_perft("3rk3/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[23, 293]);
+        assert_perft("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[23, 271]);
+        assert_perft("4k1r1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[25, 320]);
+        assert_perft("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[4, 60]);
+    }
+
+    #[test]
+    fn en_passant_follows_the_double_push() {
+        // only a pawn that just made a double push can be taken en passant
+        assert_perft("4k3/8/8/3Pp3/8/8/8/4K3 w - - 0 1", &[6, 35]);
+        assert_perft("4k3/8/8/3Pp3/8/8/8/4K3 w - e6 0 1", &[7, 38]);
+        // taking would clear the rank between the king and the rook
+        assert_perft("4k3/8/8/K2Pp2r/8/8/8/8 w - e6 0 1", &[6, 89]);
+        // e6-e5 is a single push, so d5 cannot take on e6 next move
+        assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3P4/1pN1P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 1 1", &[42, 1_880]);
+    }
+
+    #[test]
+    fn blocked_pawns_stay_put() {
+        // a pawn on the a-file must not take the piece in front of it, nor jump over it
+        assert_perft("4k3/8/8/8/p7/P7/8/4K3 w - - 0 1", &[5]);
+        assert_perft("4k3/8/8/8/8/n7/P7/4K3 w - - 0 1", &[5]);
+    }
+
+    #[test]
+    fn make_and_unmake_keep_every_view_in_step() {
+        for fen in EVERY_KIND_OF_MOVE.iter() {
+            assert_consistent(&mut GameState::from_fen(fen).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let game_state = GameState::from_fen(KIWIPETE).unwrap();
+        let counts = divide(&game_state, 2);
+
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|&(_, nodes)| nodes).sum::<u64>(), perft(&game_state, 2));
+    }
+}
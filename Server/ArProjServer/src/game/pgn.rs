@@ -0,0 +1,597 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use game::fen::{FenError, START_FEN};
+use game::gamestate::{GameState, GameStatus};
+use game::piece::Color;
+use game::piece::Move;
+use game::san::{parse_san, to_san, SanError};
+
+// export format keeps lines under 80 characters
+const LINE_LENGTH: usize = 79;
+
+/// The Seven Tag Roster, minus `Result`, which comes from the game itself.
+/// Unknown values are written as `?` the way the PGN standard asks.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    /// `YYYY.MM.DD`, with `??` for unknown parts.
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String
+}
+
+impl Default for PgnTags {
+    fn default() -> PgnTags {
+        PgnTags {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "?".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string()
+        }
+    }
+}
+
+/// The PGN result token for a game in the given state; `*` while it is still going.
+pub fn result(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Checkmate { winner: Color::White } => "1-0",
+        GameStatus::Checkmate { winner: Color::Black } => "0-1",
+        GameStatus::Stalemate | GameStatus::Draw { .. } => "1/2-1/2",
+        GameStatus::Ongoing | GameStatus::Check => "*"
+    }
+}
+
+/// Writes the game played so far as PGN, replaying `game_state.moves` from
+/// its starting position to name them in SAN.
+///
+/// `move_times` optionally holds how long each move took; those present are
+/// written after their move as `{[%emt 0:00:12]}` clock comments.
+pub fn to_pgn(game_state: &GameState, tags: &PgnTags, move_times: &[Duration]) -> String {
+    let result = result(game_state.status);
+    let mut pgn = String::new();
+
+    let roster = [
+        ("Event", &tags.event),
+        ("Site", &tags.site),
+        ("Date", &tags.date),
+        ("Round", &tags.round),
+        ("White", &tags.white),
+        ("Black", &tags.black)
+    ];
+    for &(name, value) in roster.iter() {
+        pgn.push_str(&tag(name, value));
+    }
+    pgn.push_str(&tag("Result", result));
+    if game_state.initial_fen != START_FEN {
+        pgn.push_str(&tag("SetUp", "1"));
+        pgn.push_str(&tag("FEN", &game_state.initial_fen));
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut replay = GameState::from_fen(&game_state.initial_fen).unwrap_or_default();
+    // Black's move needs its number again when it does not follow White's straight away
+    let mut after_comment = true;
+    for (index, &played) in game_state.moves.iter().enumerate() {
+        if played.piece.color == Color::White {
+            tokens.push(format!("{}.", replay.fullmove_number));
+        } else if after_comment {
+            tokens.push(format!("{}...", replay.fullmove_number));
+        }
+
+        tokens.push(to_san(&replay, played));
+        after_comment = false;
+        if let Some(elapsed) = move_times.get(index) {
+            tokens.push(format!("{{[%emt {}]}}", clock(*elapsed)));
+            after_comment = true;
+        }
+
+        let to = played.to;
+        let _ = replay.move_piece(played.from.row, played.from.col, to.row, to.col, played.promotion());
+    }
+    tokens.push(result.to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push('\n');
+    pgn
+}
+
+/// Formats a moment as a PGN `Date` tag value (UTC).
+pub fn pgn_date(time: SystemTime) -> String {
+    let days = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() / 86_400) as i64,
+        Err(_) => return PgnTags::default().date
+    };
+
+    // days since 1970-01-01 to a civil date, counting in 400 year eras from 0000-03-01
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+fn tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn clock(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// One move of an imported game, with whatever annotations followed it.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct PgnMove {
+    /// The move as written, without move number or `!`/`?` suffixes.
+    pub san: String,
+    /// Numeric annotation glyphs, with `!`, `?` and friends turned into theirs.
+    pub nags: Vec<u32>,
+    pub comments: Vec<String>,
+    /// Alternatives to this move, each starting from the position before it.
+    /// Only filled in when variations are kept.
+    pub variations: Vec<Vec<PgnMove>>
+}
+
+/// A game read from PGN, before it is replayed.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct PgnGame {
+    /// Every tag pair in the order given, the roster included.
+    pub tags: Vec<(String, String)>,
+    /// Comments that come before the first move.
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+    /// The result token closing the movetext: `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: String
+}
+
+/// Why a PGN file or one of its games could not be loaded.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum PgnError {
+    /// The text could not be read as PGN; holds the line it went wrong on.
+    Syntax { line: usize, message: String },
+    /// The `FEN` tag does not hold a valid position.
+    BadFen(FenError),
+    /// A move that is not legal where it is played. `ply` counts from 1.
+    IllegalMove { ply: usize, error: SanError }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PgnError::Syntax { line, ref message } => write!(f, "Line {}: {}", line, message),
+            PgnError::BadFen(ref error) => write!(f, "Bad FEN tag: {}", error),
+            PgnError::IllegalMove { ply, ref error } => write!(f, "Ply {}: {}", ply, error)
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl PgnGame {
+    /// The value of the first tag called `name`.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|pair| pair.0 == name).map(|pair| pair.1.as_str())
+    }
+
+    /// The position the game starts from: the `FEN` tag if there is one.
+    pub fn start(&self) -> Result<GameState, PgnError> {
+        match self.tag("FEN") {
+            Some(fen) => GameState::from_fen(fen).map_err(PgnError::BadFen),
+            None => Ok(GameState::new())
+        }
+    }
+
+    /// Plays the main line through `GameState`, checking every move against
+    /// the move generators, and hands back the final position.
+    pub fn replay(&self) -> Result<GameState, PgnError> {
+        let mut game_state = self.start()?;
+        for ply in 0..self.moves.len() {
+            self.step(&mut game_state, ply)?;
+        }
+        Ok(game_state)
+    }
+
+    /// Plays main line move number `ply` (counting from 0) on `game_state`,
+    /// which must hold the position just before it.
+    pub fn step(&self, game_state: &mut GameState, ply: usize) -> Result<Move, PgnError> {
+        let illegal = |error| PgnError::IllegalMove { ply: ply + 1, error };
+        let chosen = parse_san(game_state, &self.moves[ply].san).map_err(illegal)?;
+
+        game_state.move_piece(chosen.from.row, chosen.from.col, chosen.to.row, chosen.to.col, chosen.promotion())
+            .map_err(|error| illegal(SanError::NoSuchMove(format!("{} ({})", self.moves[ply].san, error))))
+    }
+}
+
+/// Reads every game in a PGN file. Comments, NAGs and `!`/`?` suffixes are
+/// kept on the moves they follow; variations are kept on the move they
+/// replace when `keep_variations` is set and dropped otherwise. Moves are not
+/// checked until the game is replayed.
+pub fn parse_pgn(text: &str, keep_variations: bool) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the main line at the bottom, the variation being read on top
+    let mut lines: Vec<Vec<PgnMove>> = vec![Vec::new()];
+    let mut skipped_depth = 0;
+    let mut in_movetext = false;
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut line = 1;
+    let mut index = 0;
+    let syntax = |line, message: &str| PgnError::Syntax { line, message: message.to_string() };
+
+    while index < chars.len() {
+        let letter = chars[index];
+        let at_line_start = index == 0 || chars[index - 1] == '\n';
+
+        match letter {
+            '\n' => {
+                line += 1;
+                index += 1;
+            },
+            _ if letter.is_whitespace() => index += 1,
+            // escape lines are for other programs
+            '%' if at_line_start => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+            },
+            '[' if skipped_depth == 0 && lines.len() == 1 => {
+                if in_movetext {
+                    // a new tag section without a result: the last game was unterminated
+                    finish_game(&mut games, &mut game, &mut lines, "*");
+                    in_movetext = false;
+                }
+
+                let end = tag_pair_end(&chars, index).ok_or_else(|| syntax(line, "Unterminated tag pair"))?;
+                let pair: String = chars[index + 1..end].iter().collect();
+                game.tags.push(parse_tag(&pair).ok_or_else(|| syntax(line, "Malformed tag pair"))?);
+                index = end + 1;
+            },
+            '{' => {
+                let end = find(&chars, index, '}').ok_or_else(|| syntax(line, "Unterminated comment"))?;
+                let comment: String = chars[index + 1..end].iter().collect();
+                line += comment.matches('\n').count();
+                if skipped_depth == 0 {
+                    add_comment(&mut game, &mut lines, comment.trim());
+                }
+                index = end + 1;
+            },
+            ';' => {
+                let end = find(&chars, index, '\n').unwrap_or(chars.len());
+                let comment: String = chars[index + 1..end].iter().collect();
+                if skipped_depth == 0 {
+                    add_comment(&mut game, &mut lines, comment.trim());
+                }
+                index = end;
+            },
+            '(' => {
+                in_movetext = true;
+                if skipped_depth > 0 || !keep_variations {
+                    skipped_depth += 1;
+                } else {
+                    lines.push(Vec::new());
+                }
+                index += 1;
+            },
+            ')' => {
+                if skipped_depth > 0 {
+                    skipped_depth -= 1;
+                } else if lines.len() > 1 {
+                    let variation = lines.pop().unwrap_or_default();
+                    match lines.last_mut().and_then(|parent| parent.last_mut()) {
+                        Some(parent) => parent.variations.push(variation),
+                        None => return Err(syntax(line, "Variation before any move"))
+                    }
+                } else {
+                    return Err(syntax(line, "Unmatched ')'"));
+                }
+                index += 1;
+            },
+            _ => {
+                let start = index;
+                while index < chars.len() && !chars[index].is_whitespace() && !"{}()[];".contains(chars[index]) {
+                    index += 1;
+                }
+                let token: String = chars[start..index].iter().collect();
+                in_movetext = true;
+
+                if skipped_depth > 0 {
+                    continue;
+                }
+
+                if is_result(&token) {
+                    if lines.len() > 1 {
+                        return Err(syntax(line, "Game ended inside a variation"));
+                    }
+                    finish_game(&mut games, &mut game, &mut lines, &token);
+                    in_movetext = false;
+                    continue;
+                }
+
+                if let Some(nag) = token.strip_prefix('$') {
+                    let nag = nag.parse().map_err(|_| syntax(line, "Malformed NAG"))?;
+                    add_nag(&mut lines, nag);
+                    continue;
+                }
+
+                // the en passant marker is written as a token of its own after the capture
+                if token == "e.p." {
+                    if let Some(last) = lines.last_mut().and_then(|current| current.last_mut()) {
+                        last.san.push_str(" e.p.");
+                    }
+                    continue;
+                }
+
+                // move numbers such as "12." or "12..." may run straight into the move
+                let san = strip_move_number(&token);
+                if san.is_empty() {
+                    continue;
+                }
+
+                let suffix_start = san.find(['!', '?']).unwrap_or(san.len());
+                let current = lines.last_mut().unwrap();
+                current.push(PgnMove { san: san[..suffix_start].to_string(), ..PgnMove::default() });
+                if let Some(nag) = suffix_nag(&san[suffix_start..]) {
+                    add_nag(&mut lines, nag);
+                }
+            }
+        }
+    }
+
+    if skipped_depth > 0 || lines.len() > 1 {
+        return Err(syntax(line, "Unterminated variation"));
+    }
+    if in_movetext || !game.tags.is_empty() {
+        finish_game(&mut games, &mut game, &mut lines, "*");
+    }
+
+    Ok(games)
+}
+
+fn finish_game(games: &mut Vec<PgnGame>, game: &mut PgnGame, lines: &mut Vec<Vec<PgnMove>>, result: &str) {
+    let mut finished = ::std::mem::take(game);
+    finished.moves = ::std::mem::replace(lines, vec![Vec::new()]).pop().unwrap_or_default();
+    finished.result = result.to_string();
+    games.push(finished);
+}
+
+// comments before the first move of the game stay with the game rather than a move
+fn add_comment(game: &mut PgnGame, lines: &mut [Vec<PgnMove>], comment: &str) {
+    let in_variation = lines.len() > 1;
+    match lines.last_mut().and_then(|current| current.last_mut()) {
+        Some(last) => last.comments.push(comment.to_string()),
+        None if !in_variation => game.comments.push(comment.to_string()),
+        None => ()
+    }
+}
+
+fn add_nag(lines: &mut [Vec<PgnMove>], nag: u32) {
+    if let Some(last) = lines.last_mut().and_then(|current| current.last_mut()) {
+        last.nags.push(nag);
+    }
+}
+
+// the NAG the PGN standard gives each traditional suffix
+fn suffix_nag(suffix: &str) -> Option<u32> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None
+    }
+}
+
+fn is_result(token: &str) -> bool {
+    token == "1-0" || token == "0-1" || token == "1/2-1/2" || token == "*"
+}
+
+// the move after a leading "12." or "12...", leaving castling written with zeros alone
+fn strip_move_number(token: &str) -> &str {
+    let after_digits = token.trim_start_matches(|letter: char| letter.is_ascii_digit());
+    if after_digits.len() < token.len() && after_digits.starts_with('.') {
+        after_digits.trim_start_matches('.')
+    } else {
+        token
+    }
+}
+
+// the index of the next `target` after `from`
+fn find(chars: &[char], from: usize, target: char) -> Option<usize> {
+    chars[from + 1..].iter().position(|&letter| letter == target).map(|offset| from + 1 + offset)
+}
+
+// the `]` closing the tag pair opened at `from`, looking past any inside the quoted value
+fn tag_pair_end(chars: &[char], from: usize) -> Option<usize> {
+    let mut index = from + 1;
+    while index < chars.len() && chars[index] != '"' && chars[index] != ']' {
+        index += 1;
+    }
+
+    // skip to the closing quote, stepping over escaped characters
+    if chars.get(index) == Some(&'"') {
+        index += 1;
+        while index < chars.len() && chars[index] != '"' {
+            index += if chars[index] == '\\' { 2 } else { 1 };
+        }
+    }
+
+    chars.get(index..)?.iter().position(|&letter| letter == ']').map(|offset| index + offset)
+}
+
+// `Name "value"` with the standard's backslash escapes
+fn parse_tag(pair: &str) -> Option<(String, String)> {
+    let pair = pair.trim();
+    let quote = pair.find('"')?;
+    let name = pair[..quote].trim();
+    let quoted = pair[quote + 1..].strip_suffix('"')?;
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut value = String::new();
+    let mut escaped = false;
+    for letter in quoted.chars() {
+        if escaped || letter != '\\' {
+            value.push(letter);
+            escaped = false;
+        } else {
+            escaped = true;
+        }
+    }
+    Some((name.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::perft::test_support::play;
+
+    const OPERA_GAME: &str = "1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 \
+                              9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 \
+                              15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0";
+
+    #[test]
+    fn exports_the_roster_moves_and_clock_comments() {
+        let mut game_state = GameState::new();
+        play(&mut game_state, "e2e4 e7e5 g1f3");
+        let tags = PgnTags { event: "Casual".to_string(), date: "2026.10.18".to_string(), white: "Ada".to_string(), black: "Bo".to_string(), ..PgnTags::default() };
+        let pgn = to_pgn(&game_state, &tags, &[Duration::from_secs(3), Duration::from_secs(65)]);
+        assert_eq!(pgn, "[Event \"Casual\"]\n[Site \"?\"]\n[Date \"2026.10.18\"]\n[Round \"?\"]\n[White \"Ada\"]\n[Black \"Bo\"]\n[Result \"*\"]\n\n\
+                         1. e4 {[%emt 0:00:03]} 1... e5 {[%emt 0:01:05]} 2. Nf3 *\n");
+
+        let games = parse_pgn(&pgn, false).unwrap();
+        let names: Vec<&str> = games[0].tags.iter().map(|pair| pair.0.as_str()).collect();
+        assert_eq!(names, ["Event", "Site", "Date", "Round", "White", "Black", "Result"]);
+        assert_eq!(games[0].moves[1].comments, ["[%emt 0:01:05]"]);
+        assert_eq!(games[0].replay().ok().unwrap().to_fen(), game_state.to_fen());
+    }
+
+    #[test]
+    fn a_game_from_a_position_carries_its_fen() {
+        let mut game_state = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap();
+        play(&mut game_state, "e8d7 e2e4");
+        let pgn = to_pgn(&game_state, &PgnTags::default(), &[]);
+        assert!(pgn.ends_with("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 13. e4 *\n"), "{}", pgn);
+    }
+
+    #[test]
+    fn long_movetext_wraps_between_tokens() {
+        let game_state = parse_pgn(OPERA_GAME, false).unwrap()[0].replay().ok().unwrap();
+        let pgn = to_pgn(&game_state, &PgnTags::default(), &[]);
+        let movetext: Vec<&str> = pgn.lines().skip_while(|line| !line.is_empty()).skip(1).collect();
+
+        assert!(movetext.len() > 1);
+        assert!(movetext.iter().all(|line| line.len() <= LINE_LENGTH));
+        // each line is as full as it can be
+        for pair in movetext.windows(2) {
+            assert!(pair[0].len() + 1 + pair[1].split(' ').next().unwrap().len() > LINE_LENGTH);
+        }
+        assert_eq!(movetext.join(" "), OPERA_GAME);
+    }
+
+    #[test]
+    fn dates_are_written_in_utc() {
+        let day = |seconds| pgn_date(UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(day(0), "1970.01.01");
+        assert_eq!(day(951_825_599), "2000.02.29");
+        assert_eq!(day(1_735_689_599), "2024.12.31");
+        assert_eq!(pgn_date(UNIX_EPOCH - Duration::from_secs(1)), "????.??.??");
+    }
+
+    #[test]
+    fn comments_and_nags_stay_with_their_move() {
+        let games = parse_pgn("{Before the game} 1. e4! {Best by test} e5 $2 ; a line comment\n2. Nf3?! $14 {Hmm} *", false).unwrap();
+        let game = &games[0];
+        assert_eq!(game.comments, ["Before the game"]);
+        assert_eq!(game.moves[0], PgnMove { san: "e4".to_string(), nags: vec![1], comments: vec!["Best by test".to_string()], variations: Vec::new() });
+        assert_eq!(game.moves[1].nags, [2]);
+        assert_eq!(game.moves[1].comments, ["a line comment"]);
+        assert_eq!(game.moves[2].san, "Nf3");
+        assert_eq!(game.moves[2].nags, [6, 14]);
+        assert_eq!(game.moves[2].comments, ["Hmm"]);
+    }
+
+    #[test]
+    fn nested_variations_are_kept_or_dropped() {
+        let text = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5) 2... d6) (1... e6) 2. Nf3 *";
+
+        let kept = &parse_pgn(text, true).unwrap()[0];
+        let sans = |line: &[PgnMove]| line.iter().map(|played| played.san.clone()).collect::<Vec<_>>();
+        assert_eq!(sans(&kept.moves), ["e4", "e5", "Nf3"]);
+        assert_eq!(kept.moves[1].variations.len(), 2);
+        assert_eq!(sans(&kept.moves[1].variations[0]), ["c5", "Nf3", "d6"]);
+        assert_eq!(sans(&kept.moves[1].variations[0][1].variations[0]), ["c3", "d5"]);
+        assert_eq!(sans(&kept.moves[1].variations[1]), ["e6"]);
+
+        let dropped = &parse_pgn(text, false).unwrap()[0];
+        assert_eq!(sans(&dropped.moves), ["e4", "e5", "Nf3"]);
+        assert!(dropped.moves.iter().all(|played| played.variations.is_empty()));
+
+        assert!(parse_pgn("1. e4 (1. d4 *", true).is_err());
+    }
+
+    #[test]
+    fn a_file_may_hold_many_games() {
+        let games = parse_pgn("[White \"A\"]\n\n1. e4 e5 1-0\n\n[White \"B\"]\n\n1. d4 1/2-1/2\n\n1. c4", false).unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!((games[0].tag("White"), games[0].result.as_str(), games[0].moves.len()), (Some("A"), "1-0", 2));
+        assert_eq!((games[1].tag("White"), games[1].result.as_str(), games[1].moves.len()), (Some("B"), "1/2-1/2", 1));
+        // the last game has no result token
+        assert_eq!((games[2].tag("White"), games[2].result.as_str()), (None, "*"));
+    }
+
+    #[test]
+    fn the_result_token_is_kept_as_written() {
+        // the replay decides how the game ended; a wrong token does not stop it loading
+        let games = parse_pgn("1. f3 e5 2. g4 Qh4# 1-0", false).unwrap();
+        assert_eq!(games[0].result, "1-0");
+        let game_state = games[0].replay().ok().unwrap();
+        assert_eq!(result(game_state.status), "0-1");
+    }
+
+    #[test]
+    fn castling_may_be_written_with_zeros() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5. d3 d6 6. Nc3 Bg4 7. Be3 Qd7 8. a3 0-0-0 *", false).unwrap();
+        let game_state = games[0].replay().ok().unwrap();
+        assert_eq!(game_state.to_fen(), "2kr3r/pppq1ppp/2np1n2/2b1p3/2B1P1b1/P1NPBN2/1PP2PPP/R2Q1RK1 w - - 1 9");
+    }
+
+    #[test]
+    fn a_tag_value_may_hold_brackets_and_quotes() {
+        let games = parse_pgn("[Event \"Blitz [rated]\"]\n[Site \"The \\\"Annex\\\" ]\"]\n\n1. e4 *\n\n[Event \"Second\"]\n\n1. d4 *", false).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("Event"), Some("Blitz [rated]"));
+        assert_eq!(games[0].tag("Site"), Some("The \"Annex\" ]"));
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+
+        assert!(parse_pgn("[Event \"Blitz [rated]]\n1. e4 *", false).is_err());
+    }
+}
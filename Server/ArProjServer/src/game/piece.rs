@@ -1,6 +1,10 @@
 use std::fmt;
 
-#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+use serde::de;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PieceType {
     Pawn,
     King,
@@ -10,27 +14,215 @@ pub enum PieceType {
     Knight,
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+impl PieceType {
+    /// Reads a piece letter (`q`, `r`, `b`, `n`, `k`, `p`), in either case.
+    pub fn from_letter(letter: char) -> Option<PieceType> {
+        match letter.to_ascii_lowercase() {
+            'p' => Some(PieceType::Pawn),
+            'n' => Some(PieceType::Knight),
+            'b' => Some(PieceType::Bishop),
+            'r' => Some(PieceType::Rook),
+            'q' => Some(PieceType::Queen),
+            'k' => Some(PieceType::King),
+            _ => None
+        }
+    }
+
+    /// The uppercase letter used for the piece in FEN and SAN.
+    pub fn letter(&self) -> char {
+        match *self {
+            PieceType::Pawn => 'P',
+            PieceType::Knight => 'N',
+            PieceType::Bishop => 'B',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K'
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Color {
     White,
     Black
 }
 
+impl Color {
+    pub fn opponent(&self) -> Color {
+        match *self {
+            Color::White => Color::Black,
+            Color::Black => Color::White
+        }
+    }
+}
+
+/// A square on the grid. Row 0 is rank 8 and col 0 the a-file; outside the
+/// engine squares go by their names (`e2`), which is also how they travel
+/// over the wire.
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
 pub struct Coord {
     pub row: usize,
     pub col: usize
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+impl Coord {
+    /// Reads a square name such as `e2`.
+    pub fn from_square(name: &str) -> Option<Coord> {
+        let bytes = name.as_bytes();
+        if bytes.len() != 2 || bytes[0] < b'a' || bytes[0] > b'h' || bytes[1] < b'1' || bytes[1] > b'8' {
+            return None;
+        }
+
+        Some(Coord { row: (b'8' - bytes[1]) as usize, col: (bytes[0] - b'a') as usize })
+    }
+
+    /// The file letter, `a` to `h`.
+    pub fn file(&self) -> char {
+        (b'a' + self.col as u8) as char
+    }
+
+    /// The rank number, 1 to 8.
+    pub fn rank(&self) -> usize {
+        8 - self.row
+    }
+
+    pub fn square(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // squares typed in as raw indices can be off the board and have no name
+        if self.row < 8 && self.col < 8 {
+            write!(f, "{}{}", self.file(), self.rank())
+        } else {
+            write!(f, "({}, {})", self.row, self.col)
+        }
+    }
+}
+
+impl Serialize for Coord {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.square())
+    }
+}
+
+impl<'de> Deserialize<'de> for Coord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Coord, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Coord::from_square(&name).ok_or_else(|| de::Error::custom(format!("'{}' is not a square name", name)))
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Piece {
     pub piece_type: PieceType,
-    pub has_moved: bool,
     pub row: usize,
     pub col: usize,
     pub color: Color
 }
 
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveKind {
+    Quiet,
+    DoublePush,
+    CastleKingside,
+    CastleQueenside,
+    EnPassant,
+    Promotion(PieceType)
+}
+
+/// A move as generated for `piece`, which is the piece as it stood on `from`.
+/// `captured` is the piece taken, which for en passant is not on `to`.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Move {
+    pub from: Coord,
+    pub to: Coord,
+    pub piece: Piece,
+    pub captured: Option<Piece>,
+    pub kind: MoveKind
+}
+
+impl Move {
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.kind == MoveKind::CastleKingside || self.kind == MoveKind::CastleQueenside
+    }
+
+    pub fn promotion(&self) -> Option<PieceType> {
+        match self.kind {
+            MoveKind::Promotion(piece_type) => Some(piece_type),
+            _ => None
+        }
+    }
+
+    /// The move in UCI long algebraic notation.
+    pub fn uci(&self) -> UciMove {
+        UciMove { from: self.from, to: self.to, promotion: self.promotion() }
+    }
+
+    /// Where the castling rook starts and lands, for castling moves.
+    pub fn rook_hop(&self) -> Option<(Coord, Coord)> {
+        let row = self.from.row;
+        match self.kind {
+            MoveKind::CastleKingside => Some((Coord { row, col: 7 }, Coord { row, col: 5 })),
+            MoveKind::CastleQueenside => Some((Coord { row, col: 0 }, Coord { row, col: 3 })),
+            _ => None
+        }
+    }
+}
+
+/// A move in UCI long algebraic notation (`e2e4`, `e7e8q`, castling as the
+/// king's two-square move `e1g1`). It says where a piece goes, not which
+/// move of the position that is; match it against the generated moves.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub struct UciMove {
+    pub from: Coord,
+    pub to: Coord,
+    pub promotion: Option<PieceType>
+}
+
+impl UciMove {
+    pub fn parse(text: &str) -> Option<UciMove> {
+        if !text.is_ascii() || text.len() != 4 && text.len() != 5 {
+            return None;
+        }
+
+        let promotion = match text[4..].chars().next() {
+            Some(letter) if "qrbn".contains(letter) => PieceType::from_letter(letter),
+            Some(_) => return None,
+            None => None
+        };
+
+        Some(UciMove {
+            from: Coord::from_square(&text[0..2])?,
+            to: Coord::from_square(&text[2..4])?,
+            promotion
+        })
+    }
+
+    /// Whether `candidate` is the move this names.
+    pub fn matches(&self, candidate: &Move) -> bool {
+        candidate.from == self.from && candidate.to == self.to && candidate.promotion() == self.promotion
+    }
+}
+
+impl fmt::Display for UciMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(piece_type) => write!(f, "{}", piece_type.letter().to_ascii_lowercase()),
+            None => Ok(())
+        }
+    }
+}
+
 impl fmt::Display for Piece {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let piece_rep = if self.color == Color::White {
@@ -54,4 +246,31 @@ impl fmt::Display for Piece {
         };
         write!(f, "{}", piece_rep)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_map_onto_the_grid() {
+        assert_eq!(Coord::from_square("a1"), Some(Coord { row: 7, col: 0 }));
+        assert_eq!(Coord::from_square("h8"), Some(Coord { row: 0, col: 7 }));
+        assert_eq!(Coord { row: 6, col: 4 }.square(), "e2");
+
+        for name in ["i1", "a9", "a0", "e", "e22", "E2"].iter() {
+            assert_eq!(Coord::from_square(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn uci_moves_parse_and_print() {
+        let promotion = UciMove::parse("e7e8q").unwrap();
+        assert_eq!(promotion, UciMove { from: Coord { row: 1, col: 4 }, to: Coord { row: 0, col: 4 }, promotion: Some(PieceType::Queen) });
+        assert_eq!(promotion.to_string(), "e7e8q");
+        assert_eq!(UciMove::parse("g1f3").map(|uci| uci.promotion), Some(None));
+
+        for text in ["e7e8k", "e7e8p", "i1a1", "e2e", "e2e4e5"].iter() {
+            assert_eq!(UciMove::parse(text), None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::fmt;
+
+use game::gamestate::{GameState, GameStatus};
+use game::piece::Coord;
+use game::piece::Move;
+use game::piece::MoveKind;
+use game::piece::PieceType;
+
+/// Writes `chosen` in Standard Algebraic Notation (`Nbd7`, `exd6`, `O-O`,
+/// `e8=Q+`). `game_state` is the position before the move is played and
+/// `chosen` must be one of its legal moves.
+pub fn to_san(game_state: &GameState, chosen: Move) -> String {
+    let mut san = match chosen.kind {
+        MoveKind::CastleKingside => "O-O".to_string(),
+        MoveKind::CastleQueenside => "O-O-O".to_string(),
+        _ => {
+            let mut san = String::new();
+            if chosen.piece.piece_type == PieceType::Pawn {
+                if chosen.is_capture() {
+                    san.push(chosen.from.file());
+                }
+            } else {
+                san.push(chosen.piece.piece_type.letter());
+                san.push_str(&disambiguation(game_state, chosen));
+            }
+
+            if chosen.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&chosen.to.square());
+
+            if let Some(piece_type) = chosen.promotion() {
+                san.push('=');
+                san.push(piece_type.letter());
+            }
+            san
+        }
+    };
+
+    let mut after = game_state.clone();
+    if after.move_piece(chosen.from.row, chosen.from.col, chosen.to.row, chosen.to.col, chosen.promotion()).is_ok() {
+        if let GameStatus::Checkmate { .. } = after.status {
+            san.push('#');
+        } else if after.is_in_check(after.turn) {
+            san.push('+');
+        }
+    }
+
+    san
+}
+
+// the file, rank or both of the moving piece, when another of its kind could also reach the square
+fn disambiguation(game_state: &GameState, chosen: Move) -> String {
+    let rivals: Vec<Move> = game_state.piece_list().into_iter()
+        .filter(|other| other.piece_type == chosen.piece.piece_type && other.color == chosen.piece.color)
+        .filter(|other| other.row != chosen.from.row || other.col != chosen.from.col)
+        .filter_map(|other| game_state.possible_moves(other).into_iter().find(|candidate| candidate.to == chosen.to))
+        .collect();
+
+    if rivals.is_empty() {
+        String::new()
+    } else if rivals.iter().all(|rival| rival.from.col != chosen.from.col) {
+        chosen.from.file().to_string()
+    } else if rivals.iter().all(|rival| rival.from.row != chosen.from.row) {
+        chosen.from.rank().to_string()
+    } else {
+        chosen.from.square()
+    }
+}
+
+/// Why a SAN string does not name a legal move in the position.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum SanError {
+    /// The text is not SAN at all.
+    Malformed(String),
+    /// No legal move matches.
+    NoSuchMove(String),
+    /// More than one legal move matches; the SAN needs a file or rank to tell them apart.
+    Ambiguous(String)
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SanError::Malformed(ref san) => write!(f, "'{}' is not a move in algebraic notation", san),
+            SanError::NoSuchMove(ref san) => write!(f, "{} is not a legal move here", san),
+            SanError::Ambiguous(ref san) => write!(f, "{} could be more than one move", san)
+        }
+    }
+}
+
+impl Error for SanError {}
+
+/// Finds the legal move in `game_state` that `san` names. Check, mate and
+/// annotation suffixes (`+`, `#`, `!`, `?`) are accepted and ignored, as is
+/// castling written with zeros. A trailing `e.p.` only matches en passant
+/// captures.
+pub fn parse_san(game_state: &GameState, san: &str) -> Result<Move, SanError> {
+    let malformed = || SanError::Malformed(san.to_string());
+    let text = san.trim();
+    let en_passant = text.ends_with("e.p.");
+    let text = text.trim_end_matches("e.p.").trim_end().trim_end_matches(|letter| "+#!?".contains(letter));
+
+    let castle = match text {
+        "O-O" | "0-0" => Some(MoveKind::CastleKingside),
+        "O-O-O" | "0-0-0" => Some(MoveKind::CastleQueenside),
+        _ => None
+    };
+    if let Some(kind) = castle {
+        return find_move(game_state, san, |candidate| candidate.kind == kind);
+    }
+
+    let (piece_type, rest) = match text.chars().next() {
+        Some(letter) if "NBRQK".contains(letter) => (PieceType::from_letter(letter).ok_or_else(malformed)?, &text[1..]),
+        Some(_) => (PieceType::Pawn, text),
+        None => return Err(malformed())
+    };
+
+    // the promotion piece follows the destination, with or without '='
+    let (rest, promotion) = match rest.char_indices().last() {
+        Some((index, letter)) if piece_type == PieceType::Pawn && "NBRQ".contains(letter) => {
+            (rest[..index].trim_end_matches('='), PieceType::from_letter(letter))
+        },
+        _ => (rest, None)
+    };
+
+    let rest: String = rest.chars().filter(|&letter| letter != 'x' && letter != '-').collect();
+    if rest.len() < 2 || !rest.is_ascii() {
+        return Err(malformed());
+    }
+    let (from_hint, to) = rest.split_at(rest.len() - 2);
+    let to = Coord::from_square(to).ok_or_else(malformed)?;
+
+    let mut from_col = None;
+    let mut from_row = None;
+    for letter in from_hint.chars() {
+        match letter {
+            'a'..='h' if from_col.is_none() => from_col = Some(letter as usize - 'a' as usize),
+            '1'..='8' if from_row.is_none() => from_row = Some(8 - (letter as usize - '0' as usize)),
+            _ => return Err(malformed())
+        }
+    }
+
+    find_move(game_state, san, |candidate| {
+        candidate.piece.piece_type == piece_type
+            && candidate.to == to
+            && candidate.promotion() == promotion
+            && !candidate.is_castle()
+            && (!en_passant || candidate.kind == MoveKind::EnPassant)
+            && from_col.is_none_or(|col| candidate.from.col == col)
+            && from_row.is_none_or(|row| candidate.from.row == row)
+    })
+}
+
+// the single legal move for the side to move that passes the filter
+fn find_move<F: Fn(&Move) -> bool>(game_state: &GameState, san: &str, matches: F) -> Result<Move, SanError> {
+    let mut found = game_state.piece_list().into_iter()
+        .filter(|piece| piece.color == game_state.turn)
+        .flat_map(|piece| game_state.possible_moves(piece))
+        .filter(|candidate| matches(candidate));
+
+    match (found.next(), found.next()) {
+        (Some(chosen), None) => Ok(chosen),
+        (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+        (None, _) => Err(SanError::NoSuchMove(san.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // reads `san` in the position and checks both the move it names and how to_san writes that move back
+    fn assert_san(fen: &str, san: &str, uci: &str, written: &str) {
+        let game_state = GameState::from_fen(fen).ok().unwrap();
+        let chosen = parse_san(&game_state, san).unwrap();
+        assert_eq!(chosen.uci().to_string(), uci, "{}", san);
+        assert_eq!(to_san(&game_state, chosen), written);
+    }
+
+    #[test]
+    fn reads_and_writes_each_kind_of_move() {
+        assert_san("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e4", "e2e4", "e4");
+        assert_san("rnbqkb1r/ppp1pppp/5n2/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 2 3", "Nbd7", "b8d7", "Nbd7");
+        assert_san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "exd6 e.p.", "e5d6", "exd6");
+        assert_san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "O-O-O", "e8c8", "O-O-O");
+        assert_san("r3k3/8/8/8/8/8/8/4K3 b q - 0 1", "0-0-0", "e8c8", "O-O-O");
+        assert_san("4k2r/8/8/8/8/8/8/4K3 b k - 0 1", "0-0", "e8g8", "O-O");
+        assert_san("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1", "e8=N+", "e7e8n", "e8=N+");
+        assert_san("r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4", "Qxf7#", "h5f7", "Qxf7#");
+    }
+
+    #[test]
+    fn names_the_file_rank_or_square_when_needed() {
+        let knights = "rnbqkb1r/ppp1pppp/5n2/3p4/3P4/5N2/PPP1PPPP/RNBQKB1R b KQkq - 2 3";
+        assert_san(knights, "Nfd7", "f6d7", "Nfd7");
+
+        let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_san(rooks, "R1a3", "a1a3", "R1a3");
+        assert_san(rooks, "R5a3", "a5a3", "R5a3");
+
+        let queens = "8/7k/8/8/8/Q7/8/Q1Q4K w - - 0 1";
+        assert_san(queens, "Qa1b2", "a1b2", "Qa1b2");
+        assert_san(queens, "Qcb2", "c1b2", "Qcb2");
+        assert_eq!(parse_san(&GameState::from_fen(queens).ok().unwrap(), "Qb2"), Err(SanError::Ambiguous("Qb2".to_string())));
+    }
+}
@@ -0,0 +1,138 @@
+use game::attack::pawn_attacks;
+use game::bitboard::{square_index, Bitboards, EMPTY};
+use game::castling::{CastlingRights, CASTLING_CORNERS};
+use game::gamestate::GameState;
+use game::piece::Color;
+use game::piece::Coord;
+use game::piece::Move;
+use game::piece::PieceType;
+
+// one key per color, piece type and square, then the four castling rights,
+// the eight en passant files and the side to move
+const PIECE_KEYS: usize = 0;
+const CASTLING_KEYS: usize = 2 * 6 * 64;
+const EN_PASSANT_KEYS: usize = CASTLING_KEYS + 4;
+const BLACK_TO_MOVE_KEY: usize = EN_PASSANT_KEYS + 8;
+
+const KEYS: [u64; BLACK_TO_MOVE_KEY + 1] = keys();
+
+// fixed pseudo-random keys from splitmix64, so a position hashes the same in every build and run
+const fn keys() -> [u64; BLACK_TO_MOVE_KEY + 1] {
+    let mut keys = [0; BLACK_TO_MOVE_KEY + 1];
+    let mut state: u64 = 0x4152_5072_6f6a_4b69;
+    let mut index = 0;
+    while index < keys.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mixed = state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[index] = mixed ^ (mixed >> 31);
+        index += 1;
+    }
+    keys
+}
+
+fn piece_key(color: Color, piece_type: PieceType, row: usize, col: usize) -> u64 {
+    KEYS[PIECE_KEYS + (color as usize * 6 + piece_type as usize) * 64 + square_index(row, col)]
+}
+
+fn castling_key(castling: CastlingRights) -> u64 {
+    CASTLING_CORNERS.iter().enumerate()
+        .filter(|&(_, &(_, color, kind, _, _))| castling.allows(color, kind))
+        .fold(0, |key, (right, _)| key ^ KEYS[CASTLING_KEYS + right])
+}
+
+// the en passant file only counts when a pawn of the side to move stands ready to take,
+// so a double push nobody can answer leaves the key as if it were a single one
+fn en_passant_key(bitboards: &Bitboards, en_passant: Option<Coord>, turn: Color) -> u64 {
+    match en_passant {
+        Some(target) if pawn_attacks(turn.opponent(), square_index(target.row, target.col)) & bitboards.pieces(turn, PieceType::Pawn) != EMPTY => {
+            KEYS[EN_PASSANT_KEYS + target.col]
+        },
+        _ => 0
+    }
+}
+
+fn turn_key(turn: Color) -> u64 {
+    match turn {
+        Color::White => 0,
+        Color::Black => KEYS[BLACK_TO_MOVE_KEY]
+    }
+}
+
+/// The Zobrist key of the position worked out from scratch: the pieces on
+/// the board, the side to move, the castling rights and a usable en passant
+/// square. `make_move` keeps `GameState::hash` equal to this as it goes.
+pub fn position_hash(game_state: &GameState) -> u64 {
+    let mut hash = state_hash(game_state);
+    for row in game_state.grid.iter() {
+        for piece in row.iter().flatten() {
+            hash ^= piece_key(piece.color, piece.piece_type, piece.row, piece.col);
+        }
+    }
+    hash
+}
+
+/// The part of the key that depends on castling rights, the en passant
+/// square and the side to move. `make_move` takes it out before the move
+/// and puts the new one in after.
+pub fn state_hash(game_state: &GameState) -> u64 {
+    turn_key(game_state.turn)
+        ^ castling_key(game_state.castling)
+        ^ en_passant_key(&game_state.bitboards, game_state.en_passant, game_state.turn)
+}
+
+/// How the pieces' part of the key changes when `chosen` is played, the
+/// same squares `Bitboards::apply` flips.
+pub fn move_hash(chosen: &Move) -> u64 {
+    let piece = chosen.piece;
+    let mut hash = piece_key(piece.color, piece.piece_type, chosen.from.row, chosen.from.col)
+        ^ piece_key(piece.color, chosen.promotion().unwrap_or(piece.piece_type), chosen.to.row, chosen.to.col);
+
+    if let Some(captured) = chosen.captured {
+        hash ^= piece_key(captured.color, captured.piece_type, captured.row, captured.col);
+    }
+    if let Some((rook_from, rook_to)) = chosen.rook_hop() {
+        hash ^= piece_key(piece.color, PieceType::Rook, rook_from.row, rook_from.col)
+            ^ piece_key(piece.color, PieceType::Rook, rook_to.row, rook_to.col);
+    }
+    hash
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::perft::test_support::play;
+
+    #[test]
+    fn transpositions_share_a_hash() {
+        let mut knights_first = GameState::new();
+        play(&mut knights_first, "g1f3 g8f6 e2e3");
+        let mut pawn_first = GameState::new();
+        play(&mut pawn_first, "e2e3 g8f6 g1f3");
+        assert_eq!(knights_first.hash, pawn_first.hash);
+
+        // the same pieces with the other side to move, or after a rook has been and come back, are other positions
+        let mut shuffled = GameState::new();
+        play(&mut shuffled, "g1f3 g8f6 f3g1");
+        let hash_of = |fen: &str| GameState::from_fen(fen).unwrap().hash;
+        assert_eq!(shuffled.hash, hash_of("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 3 2"));
+        assert_ne!(shuffled.hash, hash_of("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 3 2"));
+
+        play(&mut shuffled, "h8g8 g1f3 g8h8 f3g1");
+        assert_eq!(shuffled.hash, hash_of("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQq - 7 4"));
+        assert_ne!(shuffled.hash, hash_of("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 7 4"));
+    }
+
+    #[test]
+    fn an_unanswerable_double_push_leaves_no_en_passant_key() {
+        let with_square = GameState::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        let without = GameState::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(with_square.hash, without.hash);
+
+        let answerable = GameState::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let answered_not = GameState::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(answerable.hash, answered_not.hash);
+    }
+}
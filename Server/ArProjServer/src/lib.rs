@@ -0,0 +1,10 @@
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+pub mod ai;
+pub mod game;
+pub mod repl;
+pub mod server;
+pub mod uci;
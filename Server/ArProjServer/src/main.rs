@@ -1,131 +1,90 @@
-mod game;
-use game::gamestate::GameState;
+extern crate ar_proj_server;
 
-fn main() {
-    let mut game_state = GameState::new();
-   
-    // loop for input
-    use std::io::{stdin};
-    let mut s = String::new();
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use ar_proj_server::game::gamestate::GameState;
+use ar_proj_server::game::pgn::{parse_pgn, result};
+use ar_proj_server::repl;
+use ar_proj_server::server;
+use ar_proj_server::uci;
 
-    println!("<<{:?}'s Turn>>", game_state.turn);
-    println!("{}", game_state);
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    loop {
-        s.clear();
-        println!("(m)ove; (s)how moves; (p)rint board; (e)xit");
-        match stdin().read_line(&mut s) {
-            Ok(_) => {
-                let input = s.trim_right();
-                match input.chars().next().unwrap() {
-                    'm' => {
-                        let mut x = String::new(); 
-                        let mut vals = input.split_whitespace().collect::<Vec<&str>>();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--repl") => repl::run(),
+        Some("--uci") => uci::run(),
+        Some("--pgn") => {
+            match args.get(1) {
+                Some(path) => check_pgn(path),
+                None => println!("[Error] --pgn expects a PGN file")
+            }
+        },
+        Some("--perft") => {
+            match args.get(1).and_then(|depth| depth.parse::<usize>().ok()) {
+                Some(depth) => run_perft(depth, &args[2..].join(" ")),
+                None => println!("[Error] --perft expects a depth, then optionally a FEN")
+            }
+        },
+        Some("--port") => {
+            match args.get(1).and_then(|port| port.parse::<u16>().ok()) {
+                Some(port) => serve(port),
+                None => println!("[Error] --port expects a port number")
+            }
+        },
+        None => serve(server::DEFAULT_PORT),
+        Some(other) => {
+            println!("[Error] Unknown argument: {}", other);
+            println!("usage: ar_proj_server [--repl | --uci | --port <port> | --pgn <file> | --perft <depth> [fen]]");
+        }
+    }
+}
 
-                        if vals.len() != 5 {
-                            println!("Enter values: (format => row1 col1 row2 col2)");
-                            match stdin().read_line(&mut x) {
-                                Ok(_) => {
-                                    vals = x.split_whitespace().collect();
-                                },
-                                Err(error) => println!("[Error] {}", error)
-                            }
-                        } else {
-                            vals.remove(0); 
-                        }
+fn serve(port: u16) {
+    if let Err(error) = server::run(("0.0.0.0", port)) {
+        println!("[Error] {}", error);
+    }
+}
 
-                        if vals.len() != 4 {
-                            println!("[Error] Not enough arguments");
-                            continue;
-                        }
+// replays every game in the file through the rules engine and reports the ones it refuses
+fn check_pgn(path: &str) {
+    let games = match fs::read_to_string(path).map(|text| parse_pgn(&text, false)) {
+        Ok(Ok(games)) => games,
+        Ok(Err(error)) => return println!("[Error] {}: {}", path, error),
+        Err(error) => return println!("[Error] {}: {}", path, error)
+    };
 
-                        match vals[0].parse::<usize>() {
-                            Ok(row1) => {
-                                match vals[1].parse::<usize>() {
-                                    Ok(col1) => {
-                                        match vals[2].parse::<usize>() {
-                                            Ok(row2) => {
-                                                match vals[3].parse::<usize>() {
-                                                    Ok(col2) => {
-                                                        match game_state.grid[row1][col1] {
-                                                            Some(piece) => {
-                                                                if piece.color == game_state.turn {
-                                                                    println!("Trying to move ({}, {}) to ({}, {}):", vals[0], vals[1], vals[2], vals[3]);
-                                                                    game_state.move_piece(row1, col1, row2, col2);
-                                                                    println!("<<{:?}'s Turn>>", game_state.turn);
-                                                                    println!("{}", game_state);
-                                                                } else {
-                                                                    println!("[Error] Trying to move {:?} piece on {:?}'s turn.", piece.color, game_state.turn);
-                                                                }
-                                                            },
-                                                            None => println!("[Error] No piece at ({}, {})", row1, col1)
-                                                        }
-                                                    },
-                                                    Err(e) => println!("[Error] {}: {}", e, vals[3])
-                                                }
-                                            },
-                                            Err(e) => println!("<Error>> {}: {}", e, vals[2])
-                                        }
-                                    },
-                                    Err(e) => println!("[Error] {}: {}", e, vals[1])
-                                }
-                            },
-                            Err(e) => println!("[Error] {}: {}", e, vals[0])
-                        }
-                    },
-                    's' => {
-                        let mut x = String::new(); 
-                        let mut vals = input.split_whitespace().collect::<Vec<&str>>();
+    let mut failed = 0;
+    for (index, game) in games.iter().enumerate() {
+        let title = format!("{} - {}", game.tag("White").unwrap_or("?"), game.tag("Black").unwrap_or("?"));
+        match game.replay() {
+            Ok(game_state) => println!("Game {} ({}): {} plies, {} -> {}", index + 1, title, game.moves.len(), game.result, result(game_state.status)),
+            Err(error) => {
+                failed += 1;
+                println!("Game {} ({}): [Error] {}", index + 1, title, error);
+            }
+        }
+    }
 
-                        if vals.len() != 3 {
-                            println!("Enter values: (format => row col)");
-                            match stdin().read_line(&mut x) {
-                                Ok(_) => {
-                                    vals = x.split_whitespace().collect();
-                                },
-                                Err(error) => println!("[Error] {}", error)
-                            }
-                        } else {
-                            vals.remove(0);
-                        }
+    println!("{} of {} games replayed", games.len() - failed, games.len());
+}
 
-                        if vals.len() != 2 {
-                            println!("[Error] Not enough arguments");
-                            continue;
-                        }
+// prints the node count below each legal move, then the total
+fn run_perft(depth: usize, fen: &str) {
+    let game_state = if fen.is_empty() { Ok(GameState::new()) } else { GameState::from_fen(fen) };
+    let game_state = match game_state {
+        Ok(game_state) => game_state,
+        Err(error) => return println!("[Error] {}", error)
+    };
 
-                        match vals[0].parse::<usize>() {
-                            Ok(row) => {
-                                match vals[1].parse::<usize>() {
-                                    Ok(col) => {
-                                        game_state.grid[row][col].map(|piece| {
-                                            println!("Generating possible moves for ({}, {})", row, col);
-                                            game_state.print_moves(game_state.possible_moves(piece));
-                                        });
-                                    },
-                                    Err(e) => println!("{}: {}", e, vals[1])
-                                }
-                            },
-                            Err(e) => println!("{}: {}", e, vals[0])
-                        }
-                    },
-                    'g' => {
-                        if game_state.no_available_moves(game_state.turn) {
-                            println!("{:?} has been checkmated! gg", game_state.turn);
-                            break
-                        }
-                        continue 
-                    },
-                    'p' => { 
-                        println!("<<{:?}'s Turn>>", game_state.turn);
-                        println!("{}", game_state); 
-                        continue 
-                    },
-                    'e' => break,
-                    _ => { println!("m -> Move\ns -> Show Moves\np -> Print board\ne -> exit"); continue }
-                }
-            },
-            Err(error) => println!("[Error] {}", error)
-        }
+    let started = Instant::now();
+    let counts = game_state.divide(depth);
+    for &(chosen, nodes) in counts.iter() {
+        println!("{}: {}", chosen.uci(), nodes);
     }
-}
\ No newline at end of file
+
+    let total: u64 = if depth == 0 { 1 } else { counts.iter().map(|&(_, nodes)| nodes).sum() };
+    println!("\nNodes searched: {} ({:.2}s)", total, started.elapsed().as_secs_f64());
+}
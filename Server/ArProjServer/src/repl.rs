@@ -0,0 +1,290 @@
+use std::fs;
+
+use game::gamestate::{GameState, GameStatus};
+use game::pgn::{parse_pgn, to_pgn, PgnGame, PgnTags};
+use game::piece::{Color, Coord, PieceType, UciMove};
+use game::san::{parse_san, to_san};
+
+pub fn run() {
+    let mut game_state = GameState::new();
+    // a game loaded with 'l', and how many of its moves have been stepped through with 'n'
+    let mut loaded: Option<(PgnGame, usize)> = None;
+   
+    // loop for input
+    use std::io::{stdin};
+    let mut s = String::new();
+
+    println!("<<{:?}'s Turn>>", game_state.turn);
+    println!("{}", game_state);
+
+    loop {
+        s.clear();
+        println!("(m)ove; (s)how moves; (p)rint board; (f)en [load]; e(x)port pgn; (l)oad pgn; (n)ext move; (d)raw claim; (e)xit");
+        match stdin().read_line(&mut s) {
+            Ok(_) => {
+                let input = s.trim_end();
+                match input.chars().next().unwrap() {
+                    'm' => {
+                        let mut x = String::new(); 
+                        let mut vals = input.split_whitespace().collect::<Vec<&str>>();
+
+                        if let Some(uci) = vals.get(1).and_then(|text| UciMove::parse(text)) {
+                            play(&mut game_state, uci.from, uci.to, uci.promotion);
+                            continue;
+                        }
+
+                        // "m Nf3", "m exd6 e.p." and the like are SAN
+                        if vals.len() == 2 || vals.len() == 3 && vals[2] == "e.p." {
+                            let san = vals[1..].join(" ");
+                            match parse_san(&game_state, &san) {
+                                Ok(chosen) => play(&mut game_state, chosen.from, chosen.to, chosen.promotion()),
+                                Err(error) => println!("[Error] {}", error)
+                            }
+                            continue;
+                        }
+
+                        if vals.len() != 5 && vals.len() != 6 {
+                            println!("Enter values: (format => row1 col1 row2 col2 [promotion: q r b n], or m <san> such as m Nf3, or m <uci> such as m g1f3)");
+                            match stdin().read_line(&mut x) {
+                                Ok(_) => {
+                                    vals = x.split_whitespace().collect();
+                                },
+                                Err(error) => println!("[Error] {}", error)
+                            }
+                        } else {
+                            vals.remove(0); 
+                        }
+
+                        if vals.len() != 4 && vals.len() != 5 {
+                            println!("[Error] Not enough arguments");
+                            continue;
+                        }
+
+                        let promotion = match vals.get(4) {
+                            Some(letter) => match letter.chars().next().and_then(PieceType::from_letter) {
+                                Some(piece_type) if letter.len() == 1 => Some(piece_type),
+                                _ => {
+                                    println!("[Error] Unknown promotion piece: {}", letter);
+                                    continue;
+                                }
+                            },
+                            None => None
+                        };
+
+                        match vals[0].parse::<usize>() {
+                            Ok(row1) => {
+                                match vals[1].parse::<usize>() {
+                                    Ok(col1) => {
+                                        match vals[2].parse::<usize>() {
+                                            Ok(row2) => {
+                                                match vals[3].parse::<usize>() {
+                                                    Ok(col2) => {
+                                                        println!("Trying to move ({}, {}) to ({}, {}):", vals[0], vals[1], vals[2], vals[3]);
+                                                        play(&mut game_state, Coord { row: row1, col: col1 }, Coord { row: row2, col: col2 }, promotion);
+                                                    },
+                                                    Err(e) => println!("[Error] {}: {}", e, vals[3])
+                                                }
+                                            },
+                                            Err(e) => println!("<Error>> {}: {}", e, vals[2])
+                                        }
+                                    },
+                                    Err(e) => println!("[Error] {}: {}", e, vals[1])
+                                }
+                            },
+                            Err(e) => println!("[Error] {}: {}", e, vals[0])
+                        }
+                    },
+                    's' => {
+                        let mut x = String::new(); 
+                        let mut vals = input.split_whitespace().collect::<Vec<&str>>();
+
+                        if let Some(square) = vals.get(1).and_then(|name| Coord::from_square(name)) {
+                            if let Some(piece) = game_state.grid[square.row][square.col] {
+                                println!("Generating possible moves for {}", square);
+                                game_state.print_moves(game_state.possible_moves(piece));
+                            }
+                            continue;
+                        }
+
+                        if vals.len() != 3 {
+                            println!("Enter values: (format => row col, or a square such as e2)");
+                            match stdin().read_line(&mut x) {
+                                Ok(_) => {
+                                    vals = x.split_whitespace().collect();
+                                },
+                                Err(error) => println!("[Error] {}", error)
+                            }
+                        } else {
+                            vals.remove(0);
+                        }
+
+                        if vals.len() != 2 {
+                            println!("[Error] Not enough arguments");
+                            continue;
+                        }
+
+                        match vals[0].parse::<usize>() {
+                            Ok(row) => {
+                                match vals[1].parse::<usize>() {
+                                    Ok(col) => {
+                                        if let Some(piece) = game_state.grid[row][col] {
+                                            println!("Generating possible moves for ({}, {})", row, col);
+                                            game_state.print_moves(game_state.possible_moves(piece));
+                                        }
+                                    },
+                                    Err(e) => println!("{}: {}", e, vals[1])
+                                }
+                            },
+                            Err(e) => println!("{}: {}", e, vals[0])
+                        }
+                    },
+                    'g' => {
+                        print_status(&game_state);
+                        if game_state.status.is_over() {
+                            break
+                        }
+                        continue 
+                    },
+                    'p' => { 
+                        println!("<<{:?}'s Turn>>", game_state.turn);
+                        println!("{}", game_state); 
+                        continue 
+                    },
+                    'f' => {
+                        // "f" alone prints the position, "f <fen>" replaces it
+                        let fen = input[1..].trim();
+                        if fen.is_empty() {
+                            println!("{}", game_state.to_fen());
+                            continue
+                        }
+
+                        match GameState::from_fen(fen) {
+                            Ok(loaded) => {
+                                game_state = loaded;
+                                println!("<<{:?}'s Turn>>", game_state.turn);
+                                println!("{}", game_state);
+                                print_status(&game_state);
+                            },
+                            Err(error) => println!("[Error] {}", error)
+                        }
+                        continue
+                    },
+                    'l' => {
+                        let vals = input.split_whitespace().collect::<Vec<&str>>();
+                        if vals.len() < 2 {
+                            println!("[Error] Usage: l <file.pgn> [game number]");
+                            continue
+                        }
+
+                        let number = match vals.get(2).map(|number| number.parse::<usize>()) {
+                            Some(Ok(number)) if number > 0 => number,
+                            Some(_) => {
+                                println!("[Error] Game numbers start at 1: {}", vals[2]);
+                                continue
+                            },
+                            None => 1
+                        };
+
+                        let games = match fs::read_to_string(vals[1]) {
+                            Ok(text) => parse_pgn(&text, false),
+                            Err(error) => {
+                                println!("[Error] {}: {}", vals[1], error);
+                                continue
+                            }
+                        };
+
+                        match games.map(|mut games| if number <= games.len() { Some(games.swap_remove(number - 1)) } else { None }) {
+                            Ok(Some(game)) => match game.start() {
+                                Ok(start) => {
+                                    println!("Loaded {} - {}, {} moves; (n)ext to step through it",
+                                        game.tag("White").unwrap_or("?"), game.tag("Black").unwrap_or("?"), game.moves.len());
+                                    game_state = start;
+                                    loaded = Some((game, 0));
+                                    println!("{}", game_state);
+                                },
+                                Err(error) => println!("[Error] {}", error)
+                            },
+                            Ok(None) => println!("[Error] {} has no game {}", vals[1], number),
+                            Err(error) => println!("[Error] {}", error)
+                        }
+                        continue
+                    },
+                    'n' => {
+                        let (game, ply) = match loaded {
+                            Some((ref game, ref mut ply)) if *ply < game.moves.len() => (game, ply),
+                            Some(_) => {
+                                println!("End of the loaded game");
+                                continue
+                            },
+                            None => {
+                                println!("[Error] Load a game with l <file.pgn> first");
+                                continue
+                            }
+                        };
+
+                        let number = game_state.fullmove_number;
+                        match game.step(&mut game_state, *ply) {
+                            Ok(played) => {
+                                let dots = if played.piece.color == Color::White { "." } else { "..." };
+                                println!("{}{} {}", number, dots, game.moves[*ply].san);
+                                *ply += 1;
+                                println!("{}", game_state);
+                                print_status(&game_state);
+                                if *ply == game.moves.len() {
+                                    println!("Result: {}", game.result);
+                                }
+                            },
+                            Err(error) => println!("[Error] {}", error)
+                        }
+                        continue
+                    },
+                    'x' => {
+                        print!("{}", to_pgn(&game_state, &PgnTags::default(), &[]));
+                        continue
+                    },
+                    'd' => {
+                        match game_state.claim_draw() {
+                            Some(reason) => {
+                                println!("{:?} claims a draw by {}", game_state.turn, reason);
+                                break
+                            },
+                            None => println!("[Error] No draw can be claimed in this position")
+                        }
+                        continue
+                    },
+                    'e' => break,
+                    _ => { println!("m -> Move, by row/col, in SAN (m Nf3) or in UCI (m g1f3)\ns -> Show Moves\np -> Print board\nf -> Print FEN, or f <fen> to load one\nx -> Export the game as PGN\nl -> Load a game from a PGN file\nn -> Play the next move of the loaded game\nd -> Claim a draw\ne -> exit"); continue }
+                }
+            },
+            Err(error) => println!("[Error] {}", error)
+        }
+    }
+}
+
+// plays the move and shows the new position, naming the move in SAN
+fn play(game_state: &mut GameState, from: Coord, to: Coord, promotion: Option<PieceType>) {
+    let before = game_state.clone();
+    match game_state.move_piece(from.row, from.col, to.row, to.col, promotion) {
+        Ok(played) => {
+            println!("{:?} played {}", played.piece.color, to_san(&before, played));
+            println!("<<{:?}'s Turn>>", game_state.turn);
+            println!("{}", game_state);
+            print_status(game_state);
+        },
+        Err(error) => println!("[Error] {}", error)
+    }
+}
+
+fn print_status(game_state: &GameState) {
+    match game_state.status {
+        GameStatus::Ongoing => println!("{:?} to move", game_state.turn),
+        GameStatus::Check => println!("{:?} is in check", game_state.turn),
+        GameStatus::Checkmate { winner } => println!("{:?} has been checkmated! {:?} wins, gg", winner.opponent(), winner),
+        GameStatus::Stalemate => println!("{:?} has no moves but is not in check: stalemate, the game is drawn", game_state.turn),
+        GameStatus::Draw { reason } => println!("The game is drawn by {}", reason)
+    }
+
+    if let Some(reason) = game_state.claimable_draw() {
+        println!("{:?} may claim a draw by {} with (d)raw", game_state.turn, reason);
+    }
+}
@@ -0,0 +1,362 @@
+pub mod protocol;
+pub mod session;
+
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use ai::level::Level;
+use game::gamestate::GameState;
+use game::piece::Color;
+use server::protocol::{ClientMessage, Envelope, MoveRejection, RejectReason, ServerMessage, PROTOCOL_VERSION};
+use server::session::{wake_bot, Room, SessionManager};
+
+pub const DEFAULT_PORT: u16 = 4243;
+
+// how often rooms are checked for players whose grace period ran out
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(5);
+
+// how long a client may leave our writes unread before its connection is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// how long a client may stay silent before it is pinged; staying silent that long again drops it
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+static NEXT_CONNECTION: AtomicUsize = AtomicUsize::new(0);
+
+pub fn run<A: ToSocketAddrs>(addr: A) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Listening on {}", listener.local_addr()?);
+
+    let sessions = Arc::new(Mutex::new(SessionManager::new()));
+
+    {
+        let sessions = Arc::clone(&sessions);
+        thread::spawn(move || loop {
+            thread::sleep(EXPIRY_INTERVAL);
+            sessions.lock().unwrap().expire_disconnected(Instant::now());
+        });
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let sessions = Arc::clone(&sessions);
+                thread::spawn(move || handle_connection(&sessions, stream));
+            },
+            Err(error) => println!("[Error] {}", error)
+        }
+    }
+
+    Ok(())
+}
+
+// what a connection is doing in its room
+#[derive(Copy, Clone)]
+enum Role {
+    Player(Color),
+    Spectator(usize)
+}
+
+// the room a connection belongs to
+struct Seat {
+    room: Arc<Mutex<Room>>,
+    role: Role
+}
+
+/// Where the messages for one connection are queued. The connection's own
+/// thread writes them to the socket, so sending never blocks, even under a
+/// room's lock.
+#[derive(Clone)]
+pub struct Outbox {
+    sender: mpsc::Sender<String>
+}
+
+impl Outbox {
+    pub fn new(sender: mpsc::Sender<String>) -> Outbox {
+        Outbox { sender }
+    }
+
+    pub fn send<T: Serialize>(&self, message: &T) {
+        if let Ok(mut line) = serde_json::to_string(&Envelope::new(message)) {
+            line.push('\n');
+            // a closed connection is noticed (and tidied up) by its own reader thread
+            let _ = self.sender.send(line);
+        }
+    }
+}
+
+// hands writing to the socket over to a thread of its own, so a client that stops
+// reading holds up nobody else; once a write fails or times out the socket is shut,
+// which ends the reader as well
+fn start_writer(stream: &TcpStream) -> io::Result<Outbox> {
+    let mut writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+
+    let (sender, receiver) = mpsc::channel::<String>();
+    thread::spawn(move || {
+        for line in receiver {
+            if writer.write_all(line.as_bytes()).is_err() {
+                let _ = writer.shutdown(Shutdown::Both);
+                break;
+            }
+        }
+    });
+
+    Ok(Outbox::new(sender))
+}
+
+fn handle_connection(sessions: &Arc<Mutex<SessionManager>>, stream: TcpStream) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let connection = NEXT_CONNECTION.fetch_add(1, Ordering::Relaxed);
+    let outbox = match stream.set_read_timeout(Some(HEARTBEAT_INTERVAL)).and_then(|_| start_writer(&stream)) {
+        Ok(outbox) => outbox,
+        Err(error) => {
+            println!("[Error] {}", error);
+            return;
+        }
+    };
+    let mut seat: Option<Seat> = None;
+
+    let mut reader = BufReader::new(stream);
+    let mut bytes = Vec::new();
+    let mut pinged = false;
+    loop {
+        match reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => break,
+            Ok(_) => pinged = false,
+            // what was read before the timeout stays in `bytes` for the rest of the line to join
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+                if pinged {
+                    println!("{} stopped answering", peer);
+                    break;
+                }
+                outbox.send(&ServerMessage::Ping);
+                pinged = true;
+                continue;
+            },
+            Err(_) => break
+        }
+
+        let line = String::from_utf8_lossy(&bytes).into_owned();
+        bytes.clear();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let envelope = match serde_json::from_str::<Envelope<ClientMessage>>(&line) {
+            Ok(envelope) => envelope,
+            Err(error) => {
+                outbox.send(&ServerMessage::Error { message: format!("Malformed message: {}", error) });
+                continue;
+            }
+        };
+
+        if envelope.version != PROTOCOL_VERSION {
+            let message = format!("Unsupported protocol version {} (server speaks {})", envelope.version, PROTOCOL_VERSION);
+            outbox.send(&ServerMessage::Error { message });
+            continue;
+        }
+
+        let response = match envelope.message {
+            // any message shows the client is still there, so there is nothing more to do
+            ClientMessage::Pong => None,
+            ClientMessage::Create { .. } | ClientMessage::Join { .. } | ClientMessage::Watch { .. } | ClientMessage::Resume { .. } if seat.is_some() => {
+                Some(ServerMessage::Error { message: "Already in a room".to_string() })
+            },
+            ClientMessage::Create { fen } => {
+                let game_state = match fen {
+                    Some(ref fen) => GameState::from_fen(fen),
+                    None => Ok(GameState::new())
+                };
+
+                match game_state {
+                    Ok(game_state) => {
+                        let mut sessions = sessions.lock().unwrap();
+                        let room = sessions.create_room(game_state);
+                        println!("{} created room {} ({} open)", peer, room.lock().unwrap().code, sessions.room_count());
+                        take_seat(room, &outbox, &peer, connection, &mut seat)
+                    },
+                    Err(error) => Some(ServerMessage::Error { message: format!("Bad FEN: {}", error) })
+                }
+            },
+            ClientMessage::Join { room: code } => {
+                // joining under the manager lock keeps a closing room from being joined
+                let sessions = sessions.lock().unwrap();
+                match sessions.find_room(&code) {
+                    Some(room) => take_seat(room, &outbox, &peer, connection, &mut seat),
+                    None => Some(ServerMessage::Error { message: format!("No room with code {}", code) })
+                }
+            },
+            ClientMessage::Watch { room: code } => {
+                let sessions = sessions.lock().unwrap();
+                match sessions.find_room(&code) {
+                    Some(room) => start_watching(room, &outbox, &peer, &mut seat),
+                    None => Some(ServerMessage::Error { message: format!("No room with code {}", code) })
+                }
+            },
+            ClientMessage::Resume { room: code, token } => {
+                let sessions = sessions.lock().unwrap();
+                match sessions.find_room(&code) {
+                    Some(room) => resume_seat(room, &token, &outbox, &peer, connection, &mut seat),
+                    None => Some(ServerMessage::Error { message: format!("No room with code {}", code) })
+                }
+            },
+            ClientMessage::Leave => match seat.take() {
+                Some(seat) => {
+                    leave(sessions, seat, &peer);
+                    None
+                },
+                None => Some(ServerMessage::Error { message: "Not in a room".to_string() })
+            },
+            ClientMessage::Move { from, to, promotion } => match seat {
+                Some(Seat { ref room, role: Role::Player(color) }) => {
+                    let played = room.lock().unwrap().play(color, connection, from, to, promotion);
+                    match played {
+                        Ok(()) => {
+                            wake_bot(room);
+                            None
+                        },
+                        Err(rejection) => Some(rejection.into_message(from, to))
+                    }
+                },
+                Some(Seat { role: Role::Spectator(_), .. }) => {
+                    Some(MoveRejection::new(RejectReason::Spectator, "Spectators cannot move").into_message(from, to))
+                },
+                None => Some(ServerMessage::Error { message: "Join a room before moving".to_string() })
+            },
+            ClientMessage::Board => match seat {
+                Some(ref seat) => Some(ServerMessage::board_snapshot(&seat.room.lock().unwrap().game_state)),
+                None => Some(ServerMessage::Error { message: "Join a room to see its board".to_string() })
+            },
+            ClientMessage::ClaimDraw => match seat {
+                Some(Seat { ref room, role: Role::Player(color) }) => {
+                    room.lock().unwrap().claim_draw(color, connection).err().map(|message| ServerMessage::Error { message })
+                },
+                Some(Seat { role: Role::Spectator(_), .. }) => Some(ServerMessage::Error { message: "Spectators cannot claim a draw".to_string() }),
+                None => Some(ServerMessage::Error { message: "Join a room before claiming a draw".to_string() })
+            },
+            ClientMessage::Pgn => match seat {
+                Some(ref seat) => Some(ServerMessage::Pgn { pgn: seat.room.lock().unwrap().pgn() }),
+                None => Some(ServerMessage::Error { message: "Join a room to export its game".to_string() })
+            },
+            ClientMessage::AddBot { level } => match seat {
+                Some(Seat { ref room, role: Role::Player(_) }) => add_bot(room, level, &peer),
+                Some(Seat { role: Role::Spectator(_), .. }) => Some(ServerMessage::Error { message: "Spectators cannot add a bot".to_string() }),
+                None => Some(ServerMessage::Error { message: "Create a room before adding a bot".to_string() })
+            }
+        };
+
+        if let Some(response) = response {
+            outbox.send(&response);
+        }
+    }
+
+    match seat {
+        Some(Seat { ref room, role: Role::Player(color) }) => {
+            // the seat stays reserved so the player can resume after a dropped connection
+            let mut room = room.lock().unwrap();
+            println!("{} ({:?}) disconnected from room {}", peer, color, room.code);
+            room.disconnect(color, connection);
+        },
+        Some(seat) => leave(sessions, seat, &peer),
+        None => ()
+    }
+}
+
+fn take_seat(room: Arc<Mutex<Room>>, outbox: &Outbox, peer: &str, connection: usize, seat: &mut Option<Seat>) -> Option<ServerMessage> {
+    let color = {
+        let mut room = room.lock().unwrap();
+        let (color, token) = match room.join(outbox, connection) {
+            Ok(joined) => joined,
+            Err(message) => return Some(ServerMessage::Error { message })
+        };
+
+        println!("{} joined room {} as {:?}", peer, room.code, color);
+        outbox.send(&ServerMessage::Joined { room: room.code.clone(), color, token });
+
+        let snapshot = ServerMessage::board_snapshot(&room.game_state);
+        room.broadcast(&snapshot);
+        color
+    };
+
+    *seat = Some(Seat { room, role: Role::Player(color) });
+    None
+}
+
+fn resume_seat(room: Arc<Mutex<Room>>, token: &str, outbox: &Outbox, peer: &str, connection: usize, seat: &mut Option<Seat>) -> Option<ServerMessage> {
+    let color = {
+        let mut room = room.lock().unwrap();
+        let color = match room.resume(token, outbox, connection) {
+            Ok(color) => color,
+            Err(message) => return Some(ServerMessage::Error { message })
+        };
+
+        println!("{} resumed room {} as {:?}", peer, room.code, color);
+        outbox.send(&ServerMessage::Resumed { room: room.code.clone(), color });
+        outbox.send(&ServerMessage::board_snapshot(&room.game_state));
+        room.broadcast(&ServerMessage::PlayerReconnected { color });
+        color
+    };
+
+    *seat = Some(Seat { room, role: Role::Player(color) });
+    None
+}
+
+fn start_watching(room: Arc<Mutex<Room>>, outbox: &Outbox, peer: &str, seat: &mut Option<Seat>) -> Option<ServerMessage> {
+    let id = {
+        let mut room = room.lock().unwrap();
+        let id = room.watch(outbox);
+
+        println!("{} is watching room {} ({} watching)", peer, room.code, room.spectator_count());
+        outbox.send(&ServerMessage::Watching { room: room.code.clone() });
+        outbox.send(&ServerMessage::board_snapshot(&room.game_state));
+        id
+    };
+
+    *seat = Some(Seat { room, role: Role::Spectator(id) });
+    None
+}
+
+fn add_bot(room: &Arc<Mutex<Room>>, level: Level, peer: &str) -> Option<ServerMessage> {
+    {
+        let mut room = room.lock().unwrap();
+        let color = match room.seat_bot(level) {
+            Ok(color) => color,
+            Err(message) => return Some(ServerMessage::Error { message })
+        };
+
+        println!("{} seated a {:?} bot in room {} as {:?}", peer, level, room.code, color);
+        room.broadcast(&ServerMessage::BotJoined { color, level });
+        let snapshot = ServerMessage::board_snapshot(&room.game_state);
+        room.broadcast(&snapshot);
+    }
+
+    // the bot may have the first move
+    wake_bot(room);
+    None
+}
+
+fn leave(sessions: &Arc<Mutex<SessionManager>>, seat: Seat, peer: &str) {
+    let code = {
+        let mut room = seat.room.lock().unwrap();
+        match seat.role {
+            Role::Player(color) => {
+                println!("{} ({:?}) left room {}", peer, color, room.code);
+                room.leave(color);
+            },
+            Role::Spectator(id) => {
+                println!("{} stopped watching room {}", peer, room.code);
+                room.stop_watching(id);
+            }
+        }
+        room.code.clone()
+    };
+    sessions.lock().unwrap().close_if_empty(&code);
+}
@@ -0,0 +1,345 @@
+//! Wire format spoken between the server and the AR client.
+//!
+//! Every message is a single line of JSON terminated by `\n`. Each line is an
+//! [`Envelope`]: a `version` field carrying [`PROTOCOL_VERSION`] next to a
+//! `type` field naming the message, with the message's own fields alongside:
+//!
+//! ```text
+//! {"version":1,"type":"move","from":"e2","to":"e4"}
+//! ```
+//!
+//! See `PROTOCOL.md` at the crate root for the full contract.
+
+use ai::level::Level;
+use game::draw::DrawReason;
+use game::gamestate::{GameState, GameStatus, MoveError};
+use game::piece::{Color, Coord, Move, Piece, PieceType};
+
+/// Bumped whenever a message changes shape. The server rejects envelopes
+/// carrying any other version.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A message tagged with the protocol version it was written against.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Envelope<T> {
+    pub version: u32,
+    #[serde(flatten)]
+    pub message: T
+}
+
+impl<T> Envelope<T> {
+    pub fn new(message: T) -> Envelope<T> {
+        Envelope { version: PROTOCOL_VERSION, message }
+    }
+}
+
+/// Requests sent by the client.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Open a new room and sit down in it as White. The game starts from
+    /// `fen` if given, otherwise from the usual starting position.
+    Create {
+        #[serde(default)]
+        fen: Option<String>
+    },
+    /// Take the free seat in the room with the given code.
+    Join { room: String },
+    /// Follow the room with the given code without taking a seat.
+    Watch { room: String },
+    /// Take back a seat after a dropped connection, using the token from `joined`.
+    Resume { room: String, token: String },
+    /// Give up your seat (or stop watching) for good.
+    Leave,
+    /// Move the piece on `from` to `to`. `promotion` names the piece a pawn
+    /// reaching the back rank becomes and must be left out otherwise.
+    Move {
+        from: Coord,
+        to: Coord,
+        #[serde(default)]
+        promotion: Option<PieceType>
+    },
+    /// Ask for a fresh `board_snapshot`.
+    Board,
+    /// End the game as a draw, when `board_snapshot.draw_claim` says you may.
+    ClaimDraw,
+    /// Ask for the game so far as PGN.
+    Pgn,
+    /// Seat the computer in your room's free seat, to play it alone, at
+    /// `level` if given and at full strength otherwise.
+    AddBot {
+        #[serde(default)]
+        level: Level
+    },
+    /// Answer to `ping`.
+    Pong
+}
+
+/// Responses and events sent by the server.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Reply to `create` or `join`: the room's code, the color this socket
+    /// plays and the token needed to `resume` the seat after a disconnect.
+    Joined { room: String, color: Color, token: String },
+    /// Reply to `resume`: the seat is yours again. A `board_snapshot` follows.
+    Resumed { room: String, color: Color },
+    /// Reply to `watch`: the room this socket now follows.
+    Watching { room: String },
+    /// A player's connection dropped; their seat is held for them to resume.
+    PlayerDisconnected { color: Color },
+    /// A disconnected player resumed their seat.
+    PlayerReconnected { color: Color },
+    /// A player has left the game for good.
+    PlayerLeft { color: Color },
+    /// The computer sat down in the free seat; a `board_snapshot` follows.
+    BotJoined { color: Color, level: Level },
+    /// The full position, sent after every move and on request.
+    BoardSnapshot(Box<BoardSnapshot>),
+    /// A move was played; sent to everyone before the new `board_snapshot`.
+    MovePlayed {
+        color: Color,
+        #[serde(rename = "move")]
+        played: Move,
+        /// The move in UCI notation, such as `e7e8q`.
+        uci: String,
+        /// The move in SAN, such as `e8=Q+`.
+        san: String
+    },
+    /// Reply to a `move` that was not played, sent to its sender only.
+    MoveRejected { from: Coord, to: Coord, reason: RejectReason, message: String },
+    /// The game has ended; sent once, right after the final `board_snapshot`.
+    GameOver { winner: Option<Color>, reason: GameOverReason },
+    /// Reply to `pgn`: the game so far, finished or not.
+    Pgn { pgn: String },
+    /// A request could not be understood or is not allowed right now.
+    Error { message: String },
+    /// The client has been silent for a while; answer with `pong`.
+    Ping
+}
+
+/// Board state as seen by the client. `board[row][col]` with row 0 being
+/// Black's back rank and col 0 the a-file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BoardSnapshot {
+    pub board: [[Option<Piece>; 8]; 8],
+    /// The same position in Forsyth-Edwards Notation.
+    pub fen: String,
+    /// The position's Zobrist key as 16 hex digits; equal positions share it.
+    pub hash: String,
+    pub turn: Color,
+    pub status: GameStatus,
+    /// A draw the side to move may claim right now.
+    pub draw_claim: Option<DrawReason>,
+    pub halfmove_clock: u32,
+    pub last_move: Option<Move>,
+    /// Squares the side that just moved attacks, for highlighting threats to the side to move.
+    pub attacked: Vec<Coord>
+}
+
+/// Machine readable cause of a `move_rejected`.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    NoPiece,
+    WrongTurn,
+    IllegalDestination,
+    GameOver,
+    PromotionRequired,
+    InvalidPromotion,
+    WaitingForOpponent,
+    Spectator,
+    SeatResumedElsewhere
+}
+
+/// Why a move was refused, before it is addressed back to the mover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveRejection {
+    pub reason: RejectReason,
+    pub message: String
+}
+
+impl MoveRejection {
+    pub fn new(reason: RejectReason, message: &str) -> MoveRejection {
+        MoveRejection { reason, message: message.to_string() }
+    }
+
+    pub fn into_message(self, from: Coord, to: Coord) -> ServerMessage {
+        ServerMessage::MoveRejected { from, to, reason: self.reason, message: self.message }
+    }
+}
+
+impl From<MoveError> for MoveRejection {
+    fn from(error: MoveError) -> MoveRejection {
+        let reason = match error {
+            MoveError::NoPiece(_) => RejectReason::NoPiece,
+            MoveError::WrongTurn(_) => RejectReason::WrongTurn,
+            MoveError::IllegalDestination { .. } => RejectReason::IllegalDestination,
+            MoveError::GameOver => RejectReason::GameOver,
+            MoveError::PromotionRequired => RejectReason::PromotionRequired,
+            MoveError::InvalidPromotion(_) => RejectReason::InvalidPromotion
+        };
+
+        MoveRejection { reason, message: error.to_string() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOverReason {
+    Checkmate,
+    Stalemate,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    InsufficientMaterial
+}
+
+impl From<DrawReason> for GameOverReason {
+    fn from(reason: DrawReason) -> GameOverReason {
+        match reason {
+            DrawReason::ThreefoldRepetition => GameOverReason::ThreefoldRepetition,
+            DrawReason::FivefoldRepetition => GameOverReason::FivefoldRepetition,
+            DrawReason::FiftyMoveRule => GameOverReason::FiftyMoveRule,
+            DrawReason::SeventyFiveMoveRule => GameOverReason::SeventyFiveMoveRule,
+            DrawReason::InsufficientMaterial => GameOverReason::InsufficientMaterial
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn board_snapshot(game_state: &GameState) -> ServerMessage {
+        ServerMessage::BoardSnapshot(Box::new(BoardSnapshot::new(game_state)))
+    }
+
+    /// The `game_over` announcing `status`, if it ends the game.
+    pub fn game_over(status: GameStatus) -> Option<ServerMessage> {
+        match status {
+            GameStatus::Checkmate { winner } => Some(ServerMessage::GameOver { winner: Some(winner), reason: GameOverReason::Checkmate }),
+            GameStatus::Stalemate => Some(ServerMessage::GameOver { winner: None, reason: GameOverReason::Stalemate }),
+            GameStatus::Draw { reason } => Some(ServerMessage::GameOver { winner: None, reason: GameOverReason::from(reason) }),
+            GameStatus::Ongoing | GameStatus::Check => None
+        }
+    }
+}
+
+impl BoardSnapshot {
+    pub fn new(game_state: &GameState) -> BoardSnapshot {
+        BoardSnapshot {
+            board: game_state.grid,
+            fen: game_state.to_fen(),
+            hash: format!("{:016x}", game_state.hash),
+            turn: game_state.turn,
+            status: game_state.status,
+            draw_claim: game_state.claimable_draw(),
+            halfmove_clock: game_state.halfmove_clock,
+            last_move: game_state.last_move,
+            attacked: game_state.attacked_squares(game_state.turn.opponent())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::{self, Value};
+
+    use super::*;
+    use game::piece::MoveKind;
+
+    // serializes and reads back `value`, and checks the JSON is `expected`
+    fn assert_json<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T, expected: &str) {
+        let json = serde_json::to_string(value).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), serde_json::from_str::<Value>(expected).unwrap());
+        assert_eq!(&serde_json::from_str::<T>(&json).unwrap(), value);
+        assert_eq!(&serde_json::from_str::<T>(expected).unwrap(), value);
+    }
+
+    fn square(name: &str) -> Coord {
+        Coord::from_square(name).unwrap()
+    }
+
+    #[test]
+    fn the_documented_client_messages_read_as_written() {
+        let examples = [
+            (r#"{"version":1,"type":"create"}"#, ClientMessage::Create { fen: None }),
+            (r#"{"version":1,"type":"create","fen":"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"}"#,
+             ClientMessage::Create { fen: Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string()) }),
+            (r#"{"version":1,"type":"join","room":"K7QXM"}"#, ClientMessage::Join { room: "K7QXM".to_string() }),
+            (r#"{"version":1,"type":"move","from":"e2","to":"e4"}"#,
+             ClientMessage::Move { from: square("e2"), to: square("e4"), promotion: None }),
+            (r#"{"version":1,"type":"move","from":"g7","to":"h8","promotion":"knight"}"#,
+             ClientMessage::Move { from: square("g7"), to: square("h8"), promotion: Some(PieceType::Knight) }),
+            (r#"{"version":1,"type":"add_bot","level":"beginner"}"#, ClientMessage::AddBot { level: Level::Beginner })
+        ];
+
+        for &(json, ref message) in examples.iter() {
+            let envelope: Envelope<ClientMessage> = serde_json::from_str(json).unwrap();
+            assert_eq!(envelope, Envelope::new(message.clone()), "{}", json);
+        }
+
+        assert_json(&Envelope::new(ClientMessage::AddBot { level: Level::default() }), r#"{"version":1,"type":"add_bot","level":"master"}"#);
+        assert_json(&Envelope::new(ClientMessage::ClaimDraw), r#"{"version":1,"type":"claim_draw"}"#);
+        assert_json(&Envelope::new(ClientMessage::Pong), r#"{"version":1,"type":"pong"}"#);
+    }
+
+    #[test]
+    fn core_types_round_trip() {
+        assert_json(&square("e4"), r#""e4""#);
+        assert!(serde_json::from_str::<Coord>(r#""z9""#).is_err());
+
+        let pawn = Piece { piece_type: PieceType::Pawn, color: Color::White, row: 6, col: 4 };
+        assert_json(&pawn, r#"{"piece_type": "pawn", "color": "white", "row": 6, "col": 4}"#);
+
+        let king = Piece { piece_type: PieceType::King, color: Color::White, row: 7, col: 4 };
+        let castle = Move { from: square("e1"), to: square("g1"), piece: king, captured: None, kind: MoveKind::CastleKingside };
+        assert_json(&castle, r#"{
+            "from": "e1",
+            "to": "g1",
+            "piece": {"piece_type": "king", "color": "white", "row": 7, "col": 4},
+            "captured": null,
+            "kind": "castle_kingside"
+        }"#);
+        assert_json(&MoveKind::Promotion(PieceType::Queen), r#"{"promotion": "queen"}"#);
+
+        assert_json(&GameStatus::Ongoing, r#"{"state": "ongoing"}"#);
+        assert_json(&GameStatus::Check, r#"{"state": "check"}"#);
+        assert_json(&GameStatus::Checkmate { winner: Color::Black }, r#"{"state": "checkmate", "winner": "black"}"#);
+        assert_json(&GameStatus::Stalemate, r#"{"state": "stalemate"}"#);
+        assert_json(&GameStatus::Draw { reason: DrawReason::InsufficientMaterial }, r#"{"state": "draw", "reason": "insufficient_material"}"#);
+
+        assert_json(&Level::Club, r#""club""#);
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        let game_state = GameState::new();
+        let pawn = Piece { piece_type: PieceType::Pawn, color: Color::White, row: 6, col: 4 };
+        let push = Move { from: square("e2"), to: square("e4"), piece: pawn, captured: None, kind: MoveKind::DoublePush };
+
+        assert_json(&Envelope::new(ServerMessage::BotJoined { color: Color::Black, level: Level::Casual }),
+                    r#"{"version":1,"type":"bot_joined","color":"black","level":"casual"}"#);
+        assert_json(&Envelope::new(ServerMessage::MoveRejected { from: square("e2"), to: square("e5"), reason: RejectReason::IllegalDestination, message: "No".to_string() }),
+                    r#"{"version":1,"type":"move_rejected","from":"e2","to":"e5","reason":"illegal_destination","message":"No"}"#);
+        assert_json(&Envelope::new(ServerMessage::GameOver { winner: None, reason: GameOverReason::FivefoldRepetition }),
+                    r#"{"version":1,"type":"game_over","winner":null,"reason":"fivefold_repetition"}"#);
+        assert_json(&Envelope::new(ServerMessage::Ping), r#"{"version":1,"type":"ping"}"#);
+
+        let played = ServerMessage::MovePlayed { color: Color::White, played: push, uci: "e2e4".to_string(), san: "e4".to_string() };
+        let json = serde_json::to_value(Envelope::new(played.clone())).unwrap();
+        assert_eq!(json["type"], "move_played");
+        assert_eq!(json["move"]["kind"], "double_push");
+        assert_eq!(serde_json::from_value::<Envelope<ServerMessage>>(json).unwrap().message, played);
+
+        let snapshot = ServerMessage::board_snapshot(&game_state);
+        let json = serde_json::to_value(Envelope::new(snapshot.clone())).unwrap();
+        assert_eq!(json["type"], "board_snapshot");
+        assert_eq!(json["fen"], game_state.to_fen());
+        assert_eq!(json["board"][6][4]["piece_type"], "pawn");
+        assert_eq!(serde_json::from_value::<Envelope<ServerMessage>>(json).unwrap().message, snapshot);
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use ai::level::{choose_move, Level, Rng};
+use game::gamestate::{GameState, MoveError};
+use game::pgn::{pgn_date, to_pgn, PgnTags};
+use game::san::to_san;
+use game::piece::{Color, Coord, PieceType};
+use server::protocol::{MoveRejection, RejectReason, ServerMessage};
+use server::Outbox;
+
+// letters and digits that can't be misread for one another when typed in
+const ROOM_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const ROOM_CODE_LENGTH: usize = 5;
+
+/// How long a dropped player's seat is held for them to resume it.
+pub const RESUME_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+/// How long the computer thinks about each move at full strength.
+pub const BOT_THINKING_TIME: Duration = Duration::from_secs(1);
+
+// what the bot is called in PGN exports
+const BOT_NAME: &str = "AR Chess bot";
+
+// connection ids count up from 0, so this one never belongs to a socket
+const BOT_CONNECTION: usize = usize::MAX;
+
+// a seat at the board, held by whoever presents its resume token
+struct Player {
+    color: Color,
+    token: String,
+    // id of the connection currently in the seat, so a stale socket closing late can't unseat a resumed one
+    connection: usize,
+    outbox: Option<Outbox>,
+    disconnected_at: Option<Instant>,
+    // the computer plays this seat; it has no socket and cannot be resumed
+    bot: bool
+}
+
+// a read-only socket following the game
+struct Spectator {
+    id: usize,
+    outbox: Outbox
+}
+
+pub struct Room {
+    pub code: String,
+    pub game_state: GameState,
+    players: Vec<Player>,
+    spectators: Vec<Spectator>,
+    next_spectator_id: usize,
+    created_at: SystemTime,
+    // how long each move in game_state.moves took, for the PGN clock comments
+    move_times: Vec<Duration>,
+    // when the clock for the next move started: the second player sitting down, then each move
+    turn_started_at: Option<Instant>,
+    // a search for the bot's move is running
+    bot_thinking: bool,
+    // how strongly the bot plays, once one is seated
+    bot_level: Level
+}
+
+impl Room {
+    fn new(code: String, game_state: GameState) -> Room {
+        Room {
+            code,
+            game_state,
+            players: Vec::new(),
+            spectators: Vec::new(),
+            next_spectator_id: 0,
+            created_at: SystemTime::now(),
+            move_times: Vec::new(),
+            turn_started_at: None,
+            bot_thinking: false,
+            bot_level: Level::default()
+        }
+    }
+
+    // spectators and bots alone don't keep a room open
+    pub fn is_empty(&self) -> bool {
+        self.players.iter().all(|player| player.bot)
+    }
+
+    fn open_color(&self) -> Option<Color> {
+        let taken = |color| self.players.iter().any(|player| player.color == color);
+
+        if !taken(Color::White) {
+            Some(Color::White)
+        } else if !taken(Color::Black) {
+            Some(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    pub fn broadcast(&self, message: &ServerMessage) {
+        let players = self.players.iter().filter_map(|player| player.outbox.as_ref());
+        let spectators = self.spectators.iter().map(|spectator| &spectator.outbox);
+
+        for outbox in players.chain(spectators) {
+            outbox.send(message);
+        }
+    }
+
+    // seats the connection and hands back the token that lets it resume the seat later
+    pub fn join(&mut self, outbox: &Outbox, connection: usize) -> Result<(Color, String), String> {
+        let color = match self.open_color() {
+            Some(color) => color,
+            None => return Err(format!("Room {} is full", self.code))
+        };
+
+        let token = random_token();
+        self.players.push(Player {
+            color,
+            token: token.clone(),
+            connection,
+            outbox: Some(outbox.clone()),
+            disconnected_at: None,
+            bot: false
+        });
+        self.start_clock();
+        Ok((color, token))
+    }
+
+    // the computer takes the free seat; wake_bot gets it moving
+    pub fn seat_bot(&mut self, level: Level) -> Result<Color, String> {
+        let color = match self.open_color() {
+            Some(color) => color,
+            None => return Err(format!("Room {} is full", self.code))
+        };
+
+        self.players.push(Player {
+            color,
+            token: String::new(),
+            connection: BOT_CONNECTION,
+            outbox: None,
+            disconnected_at: None,
+            bot: true
+        });
+        self.bot_level = level;
+        self.start_clock();
+        Ok(color)
+    }
+
+    // the clock starts once both seats are taken
+    fn start_clock(&mut self) {
+        if self.players.len() == 2 && self.turn_started_at.is_none() {
+            self.turn_started_at = Some(Instant::now());
+        }
+    }
+
+    // the bot's color, if it is seated, has an opponent and is the side to move in a live game
+    fn bot_to_move(&self) -> Option<Color> {
+        if self.players.len() < 2 || self.game_state.status.is_over() {
+            return None;
+        }
+
+        self.players.iter().find(|player| player.bot && player.color == self.game_state.turn).map(|player| player.color)
+    }
+
+    pub fn resume(&mut self, token: &str, outbox: &Outbox, connection: usize) -> Result<Color, String> {
+        let player = match self.players.iter_mut().find(|player| !player.bot && player.token == token) {
+            Some(player) => player,
+            None => return Err(format!("No seat in room {} for that token", self.code))
+        };
+
+        player.connection = connection;
+        player.outbox = Some(outbox.clone());
+        player.disconnected_at = None;
+        Ok(player.color)
+    }
+
+    // holds the seat open for RESUME_GRACE_PERIOD instead of freeing it
+    pub fn disconnect(&mut self, color: Color, connection: usize) {
+        let player = self.players.iter_mut().find(|player| player.color == color && player.connection == connection);
+
+        if let Some(player) = player {
+            player.outbox = None;
+            player.disconnected_at = Some(Instant::now());
+            self.broadcast(&ServerMessage::PlayerDisconnected { color });
+        }
+    }
+
+    // frees the seats of players who have been gone longer than the grace period
+    fn expire_disconnected(&mut self, now: Instant) {
+        let expired: Vec<Color> = self.players.iter()
+            .filter(|player| player.disconnected_at.is_some_and(|at| now.duration_since(at) >= RESUME_GRACE_PERIOD))
+            .map(|player| player.color)
+            .collect();
+
+        for color in expired {
+            self.leave(color);
+        }
+    }
+
+    pub fn watch(&mut self, outbox: &Outbox) -> usize {
+        let id = self.next_spectator_id;
+
+        self.next_spectator_id += 1;
+        self.spectators.push(Spectator { id, outbox: outbox.clone() });
+        id
+    }
+
+    pub fn stop_watching(&mut self, id: usize) {
+        self.spectators.retain(|spectator| spectator.id != id);
+    }
+
+    pub fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    pub fn leave(&mut self, color: Color) {
+        self.players.retain(|player| player.color != color);
+        self.broadcast(&ServerMessage::PlayerLeft { color });
+    }
+
+    pub fn play(&mut self, color: Color, connection: usize, from: Coord, to: Coord, promotion: Option<PieceType>) -> Result<(), MoveRejection> {
+        if !self.players.iter().any(|player| player.color == color && player.connection == connection) {
+            return Err(MoveRejection::new(RejectReason::SeatResumedElsewhere, "Seat was resumed from another connection"));
+        }
+
+        if self.players.len() < 2 {
+            return Err(MoveRejection::new(RejectReason::WaitingForOpponent, "Waiting for an opponent"));
+        }
+
+        // move_piece only knows whose turn it is, not who is asking
+        if color != self.game_state.turn {
+            return Err(MoveRejection::from(MoveError::WrongTurn(self.game_state.turn)));
+        }
+
+        self.record_move(color, from, to, promotion).map_err(MoveRejection::from)
+    }
+
+    // plays a move for whoever holds the seat, human or bot, and tells the room
+    fn record_move(&mut self, color: Color, from: Coord, to: Coord, promotion: Option<PieceType>) -> Result<(), MoveError> {
+        let before = self.game_state.clone();
+        let played = self.game_state.move_piece(from.row, from.col, to.row, to.col, promotion)?;
+        let san = to_san(&before, played);
+        println!("Room {}: {:?} played {}", self.code, color, san);
+
+        let now = Instant::now();
+        self.move_times.push(self.turn_started_at.map_or(Duration::from_secs(0), |started| now.duration_since(started)));
+        self.turn_started_at = Some(now);
+
+        self.broadcast(&ServerMessage::MovePlayed { color, played, uci: played.uci().to_string(), san });
+        self.broadcast(&ServerMessage::board_snapshot(&self.game_state));
+
+        if let Some(game_over) = ServerMessage::game_over(self.game_state.status) {
+            self.broadcast(&game_over);
+        }
+
+        Ok(())
+    }
+
+    pub fn pgn(&self) -> String {
+        let mut tags = PgnTags {
+            event: format!("AR Chess room {}", self.code),
+            date: pgn_date(self.created_at),
+            ..PgnTags::default()
+        };
+        for player in self.players.iter().filter(|player| player.bot) {
+            let name = format!("{} ({:?})", BOT_NAME, self.bot_level);
+            match player.color {
+                Color::White => tags.white = name,
+                Color::Black => tags.black = name
+            }
+        }
+
+        to_pgn(&self.game_state, &tags, &self.move_times)
+    }
+
+    // the side to move ends the game under the threefold or fifty-move rule
+    pub fn claim_draw(&mut self, color: Color, connection: usize) -> Result<(), String> {
+        if !self.players.iter().any(|player| player.color == color && player.connection == connection) {
+            return Err("Seat was resumed from another connection".to_string());
+        }
+
+        if color != self.game_state.turn {
+            return Err("Draws can only be claimed on your own turn".to_string());
+        }
+
+        match self.game_state.claim_draw() {
+            Some(reason) => {
+                println!("{:?} claimed a draw by {} in room {}", color, reason, self.code);
+                self.broadcast(&ServerMessage::board_snapshot(&self.game_state));
+                if let Some(game_over) = ServerMessage::game_over(self.game_state.status) {
+                    self.broadcast(&game_over);
+                }
+                Ok(())
+            },
+            None => Err("No draw can be claimed in this position".to_string())
+        }
+    }
+}
+
+/// Starts the room's bot thinking if it is its turn. The search runs on its
+/// own thread without holding the room's lock, and the move is played once
+/// it finishes. Call it after anything that can hand the bot the move.
+pub fn wake_bot(room: &Arc<Mutex<Room>>) {
+    let (game_state, level) = {
+        let mut room = room.lock().unwrap();
+        if room.bot_thinking || room.bot_to_move().is_none() {
+            return;
+        }
+        room.bot_thinking = true;
+        (room.game_state.clone(), room.bot_level)
+    };
+
+    let room = Arc::clone(room);
+    thread::spawn(move || {
+        let result = choose_move(&game_state, level, BOT_THINKING_TIME, &mut Rng::new(random_bits()));
+
+        let mut room = room.lock().unwrap();
+        room.bot_thinking = false;
+        println!("Room {}: {:?} bot searched {} plies ({} nodes), score {}", room.code, level, result.depth, result.nodes, result.score);
+
+        // a draw claim or a freed seat may have taken the move away while it thought
+        let color = match room.bot_to_move() {
+            Some(color) if room.game_state.hash == game_state.hash => color,
+            _ => return
+        };
+        if let Some(chosen) = result.best_move {
+            if let Err(error) = room.record_move(color, chosen.from, chosen.to, chosen.promotion()) {
+                println!("[Error] Room {}: bot move {} refused: {}", room.code, chosen.uci(), error);
+            }
+        }
+    });
+}
+
+/// Every game currently hosted by the server, keyed by room code.
+///
+/// Lock the manager before any room when both are needed.
+#[derive(Default)]
+pub struct SessionManager {
+    rooms: HashMap<String, Arc<Mutex<Room>>>
+}
+
+impl SessionManager {
+    pub fn new() -> SessionManager {
+        SessionManager { rooms: HashMap::new() }
+    }
+
+    pub fn create_room(&mut self, game_state: GameState) -> Arc<Mutex<Room>> {
+        let mut code = random_code();
+        while self.rooms.contains_key(&code) {
+            code = random_code();
+        }
+
+        let room = Arc::new(Mutex::new(Room::new(code.clone(), game_state)));
+        self.rooms.insert(code, Arc::clone(&room));
+        room
+    }
+
+    pub fn find_room(&self, code: &str) -> Option<Arc<Mutex<Room>>> {
+        self.rooms.get(&code.trim().to_uppercase()).cloned()
+    }
+
+    // drops the room if nobody has rejoined it since its last player left
+    pub fn close_if_empty(&mut self, code: &str) {
+        let empty = match self.rooms.get(code) {
+            Some(room) => room.lock().unwrap().is_empty(),
+            None => false
+        };
+
+        if empty {
+            self.rooms.remove(code);
+        }
+    }
+
+    pub fn room_count(&self) -> usize {
+        self.rooms.len()
+    }
+
+    // gives up on dropped players whose grace period ran out by `now`, closing rooms left empty
+    pub fn expire_disconnected(&mut self, now: Instant) {
+        self.rooms.retain(|code, room| {
+            let mut room = room.lock().unwrap();
+            room.expire_disconnected(now);
+
+            if room.is_empty() {
+                println!("Closed room {}", code);
+            }
+            !room.is_empty()
+        });
+    }
+}
+
+fn random_bits() -> u64 {
+    // RandomState is seeded from the OS, which is all the randomness codes and tokens need
+    RandomState::new().build_hasher().finish()
+}
+
+fn random_token() -> String {
+    format!("{:016x}{:016x}", random_bits(), random_bits())
+}
+
+fn random_code() -> String {
+    let mut bits = random_bits();
+    let mut code = String::with_capacity(ROOM_CODE_LENGTH);
+
+    for _ in 0..ROOM_CODE_LENGTH {
+        code.push(ROOM_CODE_ALPHABET[(bits % ROOM_CODE_ALPHABET.len() as u64) as usize] as char);
+        bits /= ROOM_CODE_ALPHABET.len() as u64;
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Receiver};
+
+    use serde_json::{self, Value};
+
+    use super::*;
+
+    // an outbox whose messages can be read back instead of going to a socket
+    fn connect() -> (Outbox, Receiver<String>) {
+        let (sender, receiver) = mpsc::channel();
+        (Outbox::new(sender), receiver)
+    }
+
+    // the `type` of every message queued since the last call
+    fn received(receiver: &Receiver<String>) -> Vec<String> {
+        receiver.try_iter()
+            .map(|line| serde_json::from_str::<Value>(&line).unwrap()["type"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn players_join_by_code_as_white_then_black() {
+        let mut sessions = SessionManager::new();
+        let code = sessions.create_room(GameState::new()).lock().unwrap().code.clone();
+        assert_eq!(code.len(), ROOM_CODE_LENGTH);
+        assert!(sessions.find_room("NOPE1").is_none());
+
+        let room = sessions.find_room(&format!(" {} ", code.to_lowercase())).unwrap();
+        let mut room = room.lock().unwrap();
+        let (white, _) = connect();
+        let (black, _) = connect();
+        let (late, _) = connect();
+
+        let (color, token) = room.join(&white, 0).unwrap();
+        assert_eq!(color, Color::White);
+        assert_eq!(token.len(), 32);
+        assert_eq!(room.join(&black, 1).unwrap().0, Color::Black);
+        assert_eq!(room.join(&late, 2).err(), Some(format!("Room {} is full", code)));
+        assert_eq!(room.seat_bot(Level::Club).err(), Some(format!("Room {} is full", code)));
+    }
+
+    #[test]
+    fn a_room_closes_once_its_players_leave() {
+        let mut sessions = SessionManager::new();
+        let room = sessions.create_room(GameState::new());
+        let code = room.lock().unwrap().code.clone();
+        let (white, _) = connect();
+        let (black, black_messages) = connect();
+        let (spectator, _) = connect();
+        {
+            let mut room = room.lock().unwrap();
+            room.join(&white, 0).unwrap();
+            room.join(&black, 1).unwrap();
+            room.watch(&spectator);
+
+            // the freed seat can be taken again
+            room.leave(Color::White);
+            assert_eq!(received(&black_messages), ["player_left"]);
+            assert_eq!(room.join(&white, 2).unwrap().0, Color::White);
+            room.leave(Color::White);
+        }
+
+        sessions.close_if_empty(&code);
+        assert_eq!(sessions.room_count(), 1);
+
+        // a spectator alone does not keep the room open
+        room.lock().unwrap().leave(Color::Black);
+        sessions.close_if_empty(&code);
+        assert_eq!(sessions.room_count(), 0);
+        assert!(sessions.find_room(&code).is_none());
+    }
+
+    #[test]
+    fn a_bot_alone_does_not_keep_a_room_open() {
+        let mut sessions = SessionManager::new();
+        let room = sessions.create_room(GameState::new());
+        let code = room.lock().unwrap().code.clone();
+        let (white, _) = connect();
+        {
+            let mut room = room.lock().unwrap();
+            room.join(&white, 0).unwrap();
+            assert_eq!(room.seat_bot(Level::Beginner), Ok(Color::Black));
+            room.leave(Color::White);
+        }
+
+        sessions.close_if_empty(&code);
+        assert_eq!(sessions.room_count(), 0);
+    }
+
+    #[test]
+    fn a_dropped_player_resumes_with_their_token() {
+        let mut sessions = SessionManager::new();
+        let room = sessions.create_room(GameState::new());
+        let mut room = room.lock().unwrap();
+        let (white, _) = connect();
+        let (black, black_messages) = connect();
+        let token = room.join(&white, 0).unwrap().1;
+        let black_token = room.join(&black, 1).unwrap().1;
+        assert_ne!(token, black_token);
+
+        room.disconnect(Color::White, 0);
+        assert_eq!(received(&black_messages), ["player_disconnected"]);
+
+        let (again, _) = connect();
+        assert!(room.resume("not the token", &again, 2).is_err());
+        assert_eq!(room.resume(&token, &again, 2), Ok(Color::White));
+
+        // the old connection lost the seat to the new one
+        let (e2, e4) = (Coord::from_square("e2").unwrap(), Coord::from_square("e4").unwrap());
+        assert_eq!(room.play(Color::White, 0, e2, e4, None).err().map(|rejection| rejection.reason), Some(RejectReason::SeatResumedElsewhere));
+        assert!(room.play(Color::White, 2, e2, e4, None).is_ok());
+    }
+
+    #[test]
+    fn a_seat_is_freed_once_the_grace_period_runs_out() {
+        let mut sessions = SessionManager::new();
+        let room = sessions.create_room(GameState::new());
+        let code = room.lock().unwrap().code.clone();
+        let (white, _) = connect();
+        let (black, black_messages) = connect();
+        let token = room.lock().unwrap().join(&white, 0).unwrap().1;
+        room.lock().unwrap().join(&black, 1).unwrap();
+
+        room.lock().unwrap().disconnect(Color::White, 0);
+        sessions.expire_disconnected(Instant::now() + RESUME_GRACE_PERIOD - Duration::from_secs(1));
+        assert_eq!(received(&black_messages), ["player_disconnected"]);
+
+        sessions.expire_disconnected(Instant::now() + RESUME_GRACE_PERIOD);
+        assert_eq!(received(&black_messages), ["player_left"]);
+        let (again, _) = connect();
+        assert!(room.lock().unwrap().resume(&token, &again, 2).is_err());
+
+        // once both seats have expired the room goes too
+        room.lock().unwrap().disconnect(Color::Black, 1);
+        sessions.expire_disconnected(Instant::now() + RESUME_GRACE_PERIOD);
+        assert!(sessions.find_room(&code).is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::{stdin, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ai::search::{search, SearchLimits, SearchResult, MATE, MAX_DEPTH};
+use game::gamestate::GameState;
+use game::piece::{Color, UciMove};
+
+// the moves a game is assumed to still need when the GUI gives a clock without `movestogo`
+const MOVES_TO_GO: u64 = 30;
+
+// a search running on its own thread, and the flag that ends it early
+struct Thinking {
+    stop: Arc<AtomicBool>,
+    // an infinite search keeps its `bestmove` to itself until told to stop
+    infinite: bool,
+    handle: JoinHandle<()>
+}
+
+// waits for the running search, if any, to print its `bestmove`; `interrupt` ends it early,
+// and an infinite search is always ended since it would otherwise never answer
+fn finish_thinking(thinking: &mut Option<Thinking>, interrupt: bool) {
+    if let Some(thinking) = thinking.take() {
+        if interrupt || thinking.infinite {
+            thinking.stop.store(true, Ordering::Relaxed);
+            thinking.handle.thread().unpark();
+        }
+        thinking.handle.join().unwrap();
+    }
+}
+
+/// Speaks the Universal Chess Interface on stdin and stdout, so the engine
+/// can be driven by chess GUIs and tournament managers.
+pub fn run() {
+    let mut game_state = GameState::new();
+    let mut thinking: Option<Thinking> = None;
+
+    let input = stdin();
+    for line in input.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.first().cloned() {
+            Some("uci") => {
+                println!("id name AR Chess {}", env!("CARGO_PKG_VERSION"));
+                println!("id author the ProjARKit authors");
+                println!("uciok");
+            },
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                finish_thinking(&mut thinking, false);
+                game_state = GameState::new();
+            },
+            Some("position") => {
+                finish_thinking(&mut thinking, false);
+                match parse_position(&words[1..]) {
+                    Ok(position) => game_state = position,
+                    Err(error) => println!("info string {}", error)
+                }
+            },
+            Some("go") => {
+                finish_thinking(&mut thinking, false);
+                let limits = parse_go(&words[1..], game_state.turn);
+                let infinite = words.contains(&"infinite") || limits == SearchLimits::default();
+                thinking = Some(go(&game_state, limits, infinite, |line| println!("{}", line)));
+            },
+            Some("stop") => finish_thinking(&mut thinking, true),
+            Some("quit") => break,
+            // options, debug output and pondering are not supported, so there is nothing to answer
+            _ => ()
+        }
+    }
+
+    finish_thinking(&mut thinking, true);
+}
+
+// searches on a new thread, handing each line of the answer to `output`
+fn go<F>(game_state: &GameState, limits: SearchLimits, infinite: bool, output: F) -> Thinking
+    where F: Fn(String) + Send + 'static
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let game_state = game_state.clone();
+
+    let flag = Arc::clone(&stop);
+    let handle = thread::spawn(move || {
+        let result = search(&game_state, limits, &flag);
+        // the search may finish on its own, having found a mate or reached its deepest,
+        // but UCI only allows the answer to an infinite search after `stop`
+        while infinite && !flag.load(Ordering::Relaxed) {
+            thread::park();
+        }
+        output(format!("info depth {} score {} nodes {}", result.depth, score(&result), result.nodes));
+        match result.best_move {
+            Some(chosen) => output(format!("bestmove {}", chosen.uci())),
+            None => output("bestmove 0000".to_string())
+        }
+    });
+
+    Thinking { stop, infinite, handle }
+}
+
+// centipawns, or the moves until mate (negative when the engine is the one getting mated)
+fn score(result: &SearchResult) -> String {
+    let plies = MATE - result.score.abs();
+    if plies > MAX_DEPTH as i32 {
+        return format!("cp {}", result.score);
+    }
+
+    let moves = (plies + 1) / 2;
+    format!("mate {}", if result.score > 0 { moves } else { -moves })
+}
+
+// `startpos` or `fen <fen>`, then optionally `moves` and the moves played since, in UCI notation
+fn parse_position(words: &[&str]) -> Result<GameState, String> {
+    let moves_at = words.iter().position(|&word| word == "moves").unwrap_or(words.len());
+    let mut game_state = match words.first() {
+        Some(&"startpos") => GameState::new(),
+        Some(&"fen") => GameState::from_fen(&words[1..moves_at].join(" ")).map_err(|error| error.to_string())?,
+        _ => return Err("position expects startpos or fen".to_string())
+    };
+
+    for &text in words.iter().skip(moves_at + 1) {
+        let uci = UciMove::parse(text).ok_or_else(|| format!("{} is not a move", text))?;
+        game_state.move_piece(uci.from.row, uci.from.col, uci.to.row, uci.to.col, uci.promotion)
+            .map_err(|error| format!("{}: {}", text, error))?;
+    }
+    Ok(game_state)
+}
+
+// `depth`, `movetime` or the clocks; with none of them the search is unlimited
+fn parse_go(words: &[&str], turn: Color) -> SearchLimits {
+    let value = |name: &str| {
+        words.iter().position(|&word| word == name)
+            .and_then(|index| words.get(index + 1))
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+
+    let (time, increment) = match turn {
+        Color::White => (value("wtime"), value("winc")),
+        Color::Black => (value("btime"), value("binc"))
+    };
+    // an even share of the clock for each move still to play, plus most of the increment,
+    // never so much that the flag could fall
+    let budget = time.map(|time| {
+        let share = time / value("movestogo").unwrap_or(MOVES_TO_GO).max(1) + increment.unwrap_or(0) * 3 / 4;
+        share.min(time.saturating_sub(50)).max(1)
+    });
+
+    SearchLimits {
+        depth: value("depth").map(|depth| depth as u32),
+        time: value("movetime").or(budget).map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn position_replays_the_moves() {
+        let from_start = parse_position(&"startpos moves e2e4 e7e5 g1f3".split_whitespace().collect::<Vec<_>>()).unwrap();
+        assert_eq!(from_start.to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+
+        let promoted = parse_position(&"fen 8/P6k/8/8/8/8/8/K7 w - - 0 1 moves a7a8q".split_whitespace().collect::<Vec<_>>()).unwrap();
+        assert_eq!(promoted.to_fen(), "Q7/7k/8/8/8/8/8/K7 b - - 0 1");
+
+        assert!(parse_position(&["startpos", "moves", "e2e5"]).is_err());
+    }
+
+    #[test]
+    fn go_turns_the_clock_into_a_time_limit() {
+        let clocks = "wtime 60000 btime 3000 winc 1000 binc 0".split_whitespace().collect::<Vec<_>>();
+        assert_eq!(parse_go(&clocks, Color::White).time, Some(Duration::from_millis(2750)));
+        assert_eq!(parse_go(&clocks, Color::Black).time, Some(Duration::from_millis(100)));
+
+        assert_eq!(parse_go(&["depth", "5"], Color::White), SearchLimits { depth: Some(5), time: None });
+        assert_eq!(parse_go(&["movetime", "500"], Color::White).time, Some(Duration::from_millis(500)));
+        assert_eq!(parse_go(&["infinite"], Color::White), SearchLimits::default());
+    }
+
+    #[test]
+    fn an_infinite_search_answers_only_after_stop() {
+        // mate in one is found at once, and the search would otherwise end there
+        let game_state = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let mut thinking = Some(go(&game_state, SearchLimits::default(), true, move |line| sender.send(line).unwrap()));
+
+        assert!(receiver.recv_timeout(Duration::from_millis(500)).is_err());
+        finish_thinking(&mut thinking, true);
+        assert_eq!(receiver.try_iter().last().unwrap(), "bestmove a1a8");
+    }
+}